@@ -0,0 +1,273 @@
+use ggez::graphics::Rect;
+use specs::{Component, DenseVecStorage, Entity};
+use std::collections::HashMap;
+
+mod state_machine;
+pub use self::state_machine::*;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PlayMode {
+    Loop,
+    // Plays forwards then backwards without repeating the end frames.
+    PingPong,
+    // Stops on the last frame.
+    Once,
+}
+
+// A run of frames from a sprite sheet. Frames are `DrawParam::src` rects, so they are
+// fractions of the sheet rather than pixels.
+pub struct Clip {
+    pub frames: Vec<Rect>,
+    pub fps: f32,
+    pub mode: PlayMode,
+}
+impl Clip {
+    pub fn new(frames: Vec<Rect>, fps: f32, mode: PlayMode) -> Self {
+        Self { frames, fps, mode }
+    }
+
+    // Frames from a sheet cut into a `columns` x `rows` grid, numbered row by row.
+    pub fn from_grid(
+        columns: usize,
+        rows: usize,
+        frames: &[usize],
+        fps: f32,
+        mode: PlayMode,
+    ) -> Self {
+        let (w, h) = (1.0 / columns as f32, 1.0 / rows as f32);
+        let frames = frames
+            .iter()
+            .map(|i| Rect::new((i % columns) as f32 * w, (i / columns) as f32 * h, w, h))
+            .collect();
+        Self::new(frames, fps, mode)
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum AnimationEventKind {
+    // A looping or ping-pong clip went back to its first frame.
+    Looped,
+    // A one-shot clip got through its last frame.
+    Finished,
+}
+
+pub struct AnimationEvent {
+    pub entity: Entity,
+    pub clip: String,
+    pub kind: AnimationEventKind,
+}
+
+// Clips that looped or finished this tick.
+#[derive(Default)]
+pub struct AnimationEvents(pub Vec<AnimationEvent>);
+
+// Steps an entity's `Sprite` through the frames of the clip being played.
+#[derive(Component)]
+pub struct Animation {
+    pub clips: HashMap<String, Clip>,
+    current: String,
+    frame: usize,
+    timer: f32,
+    forward: bool,
+    finished: bool,
+}
+impl Animation {
+    pub fn new(clips: HashMap<String, Clip>, start: &str) -> Self {
+        Self {
+            clips,
+            current: start.to_string(),
+            frame: 0,
+            timer: 0.0,
+            forward: true,
+            finished: false,
+        }
+    }
+
+    pub fn current(&self) -> &str {
+        &self.current
+    }
+    pub fn frame(&self) -> usize {
+        self.frame
+    }
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    // Switches to the named clip from its first frame. Playing the clip that is already
+    // on does nothing, so this can be called every tick. Unknown names are ignored.
+    pub fn play(&mut self, name: &str) -> bool {
+        if !self.clips.contains_key(name) {
+            return false;
+        }
+        if self.current != name {
+            self.current = name.to_string();
+            self.restart();
+        }
+        true
+    }
+
+    pub fn restart(&mut self) {
+        self.frame = 0;
+        self.timer = 0.0;
+        self.forward = true;
+        self.finished = false;
+    }
+
+    pub fn frame_rect(&self) -> Option<Rect> {
+        self.clips
+            .get(&self.current)
+            .and_then(|c| c.frames.get(self.frame))
+            .copied()
+    }
+
+    // Advances by `dt` seconds and reports whether the clip looped or finished.
+    pub fn update(&mut self, dt: f32) -> Option<AnimationEventKind> {
+        let (len, fps, mode) = match self.clips.get(&self.current) {
+            Some(c) if !c.frames.is_empty() && c.fps > 0.0 => (c.frames.len(), c.fps, c.mode),
+            _ => return None,
+        };
+        if self.finished {
+            return None;
+        }
+
+        let frame_time = 1.0 / fps;
+        let mut event = None;
+        self.timer += dt;
+        while self.timer >= frame_time {
+            self.timer -= frame_time;
+            match mode {
+                PlayMode::Loop => {
+                    self.frame = (self.frame + 1) % len;
+                    if self.frame == 0 {
+                        event = Some(AnimationEventKind::Looped);
+                    }
+                }
+                PlayMode::PingPong if len == 1 => event = Some(AnimationEventKind::Looped),
+                PlayMode::PingPong => {
+                    if self.forward && self.frame + 1 < len {
+                        self.frame += 1;
+                    } else {
+                        self.forward = false;
+                        self.frame -= 1;
+                    }
+                    // With two frames, turning at the end already brings us back to the start.
+                    if !self.forward && self.frame == 0 {
+                        self.forward = true;
+                        event = Some(AnimationEventKind::Looped);
+                    }
+                }
+                PlayMode::Once => {
+                    if self.frame + 1 < len {
+                        self.frame += 1;
+                    } else {
+                        self.finished = true;
+                        self.timer = 0.0;
+                        return Some(AnimationEventKind::Finished);
+                    }
+                }
+            }
+        }
+        event
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn animation(mode: PlayMode, frames: usize) -> Animation {
+        let mut clips = HashMap::new();
+        let indices: Vec<usize> = (0..frames).collect();
+        clips.insert(
+            "clip".to_string(),
+            Clip::from_grid(2, 2, &indices, 10.0, mode),
+        );
+        clips.insert("other".to_string(), Clip::from_grid(2, 2, &[3], 10.0, mode));
+        Animation::new(clips, "clip")
+    }
+
+    // The frame after each tick of one frame's length.
+    fn frames(anim: &mut Animation, ticks: usize) -> Vec<usize> {
+        (0..ticks)
+            .map(|_| {
+                anim.update(0.1 + 1e-4);
+                anim.frame()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn grid_frames_are_numbered_row_by_row() {
+        let clip = Clip::from_grid(2, 2, &[0, 1, 2, 3], 1.0, PlayMode::Loop);
+        assert_eq!(clip.frames[1], Rect::new(0.5, 0.0, 0.5, 0.5));
+        assert_eq!(clip.frames[2], Rect::new(0.0, 0.5, 0.5, 0.5));
+    }
+
+    #[test]
+    fn loop_wraps_around() {
+        let mut anim = animation(PlayMode::Loop, 3);
+        assert_eq!(frames(&mut anim, 4), vec![1, 2, 0, 1]);
+    }
+
+    #[test]
+    fn ping_pong_turns_at_the_ends() {
+        let mut anim = animation(PlayMode::PingPong, 3);
+        assert_eq!(frames(&mut anim, 6), vec![1, 2, 1, 0, 1, 2]);
+    }
+
+    #[test]
+    fn two_frame_ping_pong_alternates() {
+        let mut anim = animation(PlayMode::PingPong, 2);
+        assert_eq!(frames(&mut anim, 5), vec![1, 0, 1, 0, 1]);
+    }
+
+    #[test]
+    fn two_frame_ping_pong_loops_on_every_return_to_the_start() {
+        let mut anim = animation(PlayMode::PingPong, 2);
+        let events: Vec<_> = (0..4).map(|_| anim.update(0.1001)).collect();
+        let looped = Some(AnimationEventKind::Looped);
+        assert_eq!(events, vec![None, looped, None, looped]);
+    }
+
+    #[test]
+    fn once_holds_the_last_frame_and_finishes_once() {
+        let mut anim = animation(PlayMode::Once, 2);
+        assert_eq!(anim.update(0.1001), None);
+        assert_eq!(anim.update(0.1001), Some(AnimationEventKind::Finished));
+        assert_eq!(anim.update(0.1001), None);
+        assert_eq!(anim.frame(), 1);
+        assert!(anim.is_finished());
+    }
+
+    #[test]
+    fn loop_reports_each_cycle() {
+        let mut anim = animation(PlayMode::Loop, 2);
+        assert_eq!(anim.update(0.1001), None);
+        assert_eq!(anim.update(0.1001), Some(AnimationEventKind::Looped));
+    }
+
+    #[test]
+    fn frames_run_at_the_clip_rate() {
+        let mut anim = animation(PlayMode::Loop, 4);
+        anim.update(0.05);
+        assert_eq!(anim.frame(), 0);
+        anim.update(0.251);
+        assert_eq!(anim.frame(), 3);
+    }
+
+    #[test]
+    fn play_switches_by_name_and_restarts() {
+        let mut anim = animation(PlayMode::Loop, 3);
+        anim.update(0.1001);
+        assert!(anim.play("clip"));
+        assert_eq!(anim.frame(), 1);
+
+        assert!(anim.play("other"));
+        assert_eq!(anim.current(), "other");
+        assert_eq!(anim.frame(), 0);
+        assert_eq!(anim.frame_rect(), Some(Rect::new(0.5, 0.5, 0.5, 0.5)));
+
+        assert!(!anim.play("missing"));
+        assert_eq!(anim.current(), "other");
+    }
+}
@@ -0,0 +1,293 @@
+use super::{Animation, PlayMode};
+use crate::{components::Direction, physics::Vec2};
+use specs::{Component, DenseVecStorage};
+use std::collections::HashSet;
+
+// What the state machine knows about an entity this tick.
+pub struct AnimationInput {
+    pub moving: bool,
+    pub velocity: Vec2,
+    pub grounded: bool,
+    pub facing: Option<Direction>,
+}
+
+pub enum Condition {
+    Always,
+    // Has an `IntentToMove`.
+    Moving,
+    Grounded,
+    // Off the ground and going up or down.
+    Rising,
+    Falling,
+    // Horizontal speed, in world units per second.
+    SpeedAbove(f32),
+    Facing(Direction),
+    // The clip of the current state has played to its end.
+    Finished,
+    // Set by gameplay code through `AnimationStateMachine::trigger` this tick.
+    Triggered(String),
+    Not(Box<Condition>),
+    All(Vec<Condition>),
+    Any(Vec<Condition>),
+}
+
+// Cuts to the `to` state (a clip of the entity's `Animation`) when `when` holds. Without
+// a `from` it can leave any state.
+pub struct AnimTransition {
+    pub from: Option<String>,
+    pub to: String,
+    pub when: Condition,
+    pub priority: i32,
+}
+impl AnimTransition {
+    pub fn new(to: &str, when: Condition, priority: i32) -> Self {
+        Self {
+            from: None,
+            to: to.to_string(),
+            when,
+            priority,
+        }
+    }
+    pub fn between(from: &str, to: &str, when: Condition, priority: i32) -> Self {
+        Self {
+            from: Some(from.to_string()),
+            ..Self::new(to, when, priority)
+        }
+    }
+}
+
+// Picks the clip an entity's `Animation` plays. The highest priority transition that
+// applies wins, and ties go to the one listed first. A one-shot clip plays to its end
+// unless a transition of at least the priority that started it cuts in.
+#[derive(Component)]
+pub struct AnimationStateMachine {
+    pub transitions: Vec<AnimTransition>,
+    triggers: HashSet<String>,
+    priority: i32,
+}
+impl AnimationStateMachine {
+    pub fn new(transitions: Vec<AnimTransition>) -> Self {
+        Self {
+            transitions,
+            triggers: HashSet::new(),
+            priority: i32::MIN,
+        }
+    }
+
+    pub fn trigger(&mut self, name: &str) {
+        self.triggers.insert(name.to_string());
+    }
+
+    // Cuts `anim` to the state that applies, returning it if the state changed. Triggers
+    // only last for one step.
+    pub fn step(&mut self, anim: &mut Animation, input: &AnimationInput) -> Option<String> {
+        let current = anim.current();
+        let playing_once = anim
+            .clips
+            .get(current)
+            .is_some_and(|c| c.mode == PlayMode::Once && !anim.is_finished());
+
+        let mut best: Option<&AnimTransition> = None;
+        for tr in &self.transitions {
+            if tr.to == current || tr.from.as_ref().is_some_and(|f| f != current) {
+                continue;
+            }
+            if playing_once && tr.priority < self.priority {
+                continue;
+            }
+            if best.is_some_and(|b| b.priority >= tr.priority) {
+                continue;
+            }
+            if self.holds(&tr.when, anim, input) {
+                best = Some(tr);
+            }
+        }
+
+        let next = best.map(|tr| (tr.priority, tr.to.clone()));
+        self.triggers.clear();
+        let (priority, to) = next?;
+        // A state without a clip is never entered, so it mustn't guard the current one.
+        if !anim.play(&to) {
+            return None;
+        }
+        self.priority = priority;
+        Some(to)
+    }
+
+    fn holds(&self, condition: &Condition, anim: &Animation, input: &AnimationInput) -> bool {
+        use Condition::*;
+        match condition {
+            Always => true,
+            Moving => input.moving,
+            Grounded => input.grounded,
+            Rising => !input.grounded && input.velocity.y > 0.0,
+            Falling => !input.grounded && input.velocity.y < 0.0,
+            SpeedAbove(speed) => input.velocity.x.abs() > *speed,
+            Facing(dir) => input.facing == Some(*dir),
+            Finished => anim.is_finished(),
+            Triggered(name) => self.triggers.contains(name),
+            Not(c) => !self.holds(c, anim, input),
+            All(cs) => cs.iter().all(|c| self.holds(c, anim, input)),
+            Any(cs) => cs.iter().any(|c| self.holds(c, anim, input)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::animation::Clip;
+    use std::collections::HashMap;
+
+    fn animation() -> Animation {
+        let mut clips = HashMap::new();
+        for name in &["idle", "run", "jump"] {
+            clips.insert(
+                name.to_string(),
+                Clip::from_grid(2, 2, &[0, 1], 10.0, PlayMode::Loop),
+            );
+        }
+        clips.insert(
+            "attack".to_string(),
+            Clip::from_grid(2, 2, &[2, 3], 10.0, PlayMode::Once),
+        );
+        Animation::new(clips, "idle")
+    }
+
+    fn machine() -> AnimationStateMachine {
+        use Condition::*;
+        AnimationStateMachine::new(vec![
+            AnimTransition::new("attack", Triggered("attack".to_string()), 10),
+            AnimTransition::new("jump", Not(Box::new(Grounded)), 5),
+            AnimTransition::new("run", All(vec![Grounded, Moving]), 1),
+            AnimTransition::new("idle", All(vec![Grounded, Not(Box::new(Moving))]), 0),
+        ])
+    }
+
+    fn input(moving: bool, grounded: bool) -> AnimationInput {
+        AnimationInput {
+            moving,
+            velocity: Vec2::zero(),
+            grounded,
+            facing: None,
+        }
+    }
+
+    #[test]
+    fn movement_switches_between_idle_and_run() {
+        let (mut sm, mut anim) = (machine(), animation());
+        sm.step(&mut anim, &input(true, true));
+        assert_eq!(anim.current(), "run");
+        sm.step(&mut anim, &input(false, true));
+        assert_eq!(anim.current(), "idle");
+    }
+
+    #[test]
+    fn staying_in_a_state_is_not_a_transition() {
+        let (mut sm, mut anim) = (machine(), animation());
+        assert_eq!(sm.step(&mut anim, &input(false, true)), None);
+    }
+
+    #[test]
+    fn higher_priority_wins() {
+        let (mut sm, mut anim) = (machine(), animation());
+        // Both jump and run would apply if run ignored the ground.
+        sm.transitions
+            .push(AnimTransition::new("run", Condition::Moving, 1));
+        assert_eq!(
+            sm.step(&mut anim, &input(true, false)),
+            Some("jump".to_string())
+        );
+    }
+
+    #[test]
+    fn from_limits_where_a_transition_applies() {
+        let mut sm = AnimationStateMachine::new(vec![AnimTransition::between(
+            "run",
+            "jump",
+            Condition::Always,
+            0,
+        )]);
+        let mut anim = animation();
+        assert_eq!(sm.step(&mut anim, &input(false, true)), None);
+        anim.play("run");
+        assert_eq!(
+            sm.step(&mut anim, &input(false, true)),
+            Some("jump".to_string())
+        );
+    }
+
+    #[test]
+    fn one_shots_play_out_unless_cut_by_equal_priority() {
+        let (mut sm, mut anim) = (machine(), animation());
+        sm.trigger("attack");
+        sm.step(&mut anim, &input(true, true));
+        assert_eq!(anim.current(), "attack");
+
+        sm.step(&mut anim, &input(true, false));
+        assert_eq!(anim.current(), "attack");
+
+        sm.trigger("attack");
+        anim.update(0.1001);
+        sm.step(&mut anim, &input(true, true));
+        // Already attacking, so the trigger is used up without restarting.
+        assert_eq!(anim.frame(), 1);
+
+        anim.update(0.1001);
+        assert!(anim.is_finished());
+        sm.step(&mut anim, &input(true, true));
+        assert_eq!(anim.current(), "run");
+    }
+
+    #[test]
+    fn triggers_last_one_step() {
+        let (mut sm, mut anim) = (machine(), animation());
+        sm.trigger("missing");
+        sm.step(&mut anim, &input(false, true));
+        sm.transitions.push(AnimTransition::new(
+            "run",
+            Condition::Triggered("missing".to_string()),
+            20,
+        ));
+        assert_eq!(sm.step(&mut anim, &input(false, true)), None);
+    }
+
+    #[test]
+    fn facing_and_speed_conditions() {
+        let mut sm = AnimationStateMachine::new(vec![AnimTransition::new(
+            "run",
+            Condition::All(vec![
+                Condition::Facing(Direction::Left),
+                Condition::SpeedAbove(10.0),
+            ]),
+            0,
+        )]);
+        let mut anim = animation();
+        let mut inp = input(false, true);
+        inp.velocity = Vec2::new(-20.0, 0.0);
+        assert_eq!(sm.step(&mut anim, &inp), None);
+        inp.facing = Some(Direction::Left);
+        assert_eq!(sm.step(&mut anim, &inp), Some("run".to_string()));
+    }
+
+    #[test]
+    fn states_without_a_clip_leave_the_one_shot_guard_alone() {
+        use Condition::*;
+        let mut sm = AnimationStateMachine::new(vec![
+            AnimTransition::new("attack", Triggered("attack".to_string()), 10),
+            AnimTransition::new("missing", Triggered("oops".to_string()), 20),
+            AnimTransition::new("jump", Not(Box::new(Grounded)), 15),
+        ]);
+        let mut anim = animation();
+        sm.trigger("attack");
+        sm.step(&mut anim, &input(false, true));
+
+        sm.trigger("oops");
+        assert_eq!(sm.step(&mut anim, &input(false, true)), None);
+        assert_eq!(anim.current(), "attack");
+
+        // Still only the attack's priority to beat.
+        sm.step(&mut anim, &input(false, false));
+        assert_eq!(anim.current(), "jump");
+    }
+}
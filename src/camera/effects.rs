@@ -0,0 +1,214 @@
+use crate::components::Position;
+use oorandom::Rand32;
+use specs::{Component, DenseVecStorage};
+
+pub enum CameraEvent {
+    // Adds to the shake trauma, which is capped at 1.
+    Shake {
+        trauma: f32,
+    },
+    // Briefly multiplies the scale by `1 + amount`, easing back over `duration` seconds.
+    ZoomPunch {
+        amount: f32,
+        duration: f32,
+    },
+    // Pans to a world point over `duration` seconds, stays for `hold`, then pans back.
+    PanTo {
+        to: Position,
+        duration: f32,
+        hold: f32,
+    },
+}
+
+// Effects requested by gameplay code this tick. Every camera with `CameraEffects`
+// picks them up.
+#[derive(Default)]
+pub struct CameraEvents(pub Vec<CameraEvent>);
+
+struct ZoomPunch {
+    amount: f32,
+    elapsed: f32,
+    duration: f32,
+}
+
+struct Pan {
+    to: Position,
+    elapsed: f32,
+    duration: f32,
+    hold: f32,
+}
+impl Pan {
+    // How far along the way to `to` the view is, from 0 to 1 and back again.
+    fn weight(&self) -> f32 {
+        let ramp = |t: f32| {
+            let t = t.clamp(0.0, 1.0);
+            t * t * (3.0 - 2.0 * t)
+        };
+        if self.duration <= 0.0 {
+            return if self.elapsed < self.hold { 1.0 } else { 0.0 };
+        }
+        let back_at = self.duration + self.hold;
+        if self.elapsed < self.duration {
+            ramp(self.elapsed / self.duration)
+        } else if self.elapsed < back_at {
+            1.0
+        } else {
+            1.0 - ramp((self.elapsed - back_at) / self.duration)
+        }
+    }
+
+    fn is_finished(&self) -> bool {
+        self.elapsed >= self.duration * 2.0 + self.hold
+    }
+}
+
+// Offsets layered over a camera's own position and scale. The shake is driven by its
+// own seeded generator so the same seed and inputs always shake the same way.
+#[derive(Component)]
+pub struct CameraEffects {
+    pub trauma: f32,
+    // Trauma lost per second.
+    pub trauma_decay: f32,
+    // Offset in world units at full trauma.
+    pub max_shake: f32,
+    rng: Rand32,
+    punches: Vec<ZoomPunch>,
+    pan: Option<Pan>,
+}
+impl CameraEffects {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            trauma: 0.0,
+            trauma_decay: 1.5,
+            max_shake: 30.0,
+            rng: Rand32::new(seed),
+            punches: Vec::new(),
+            pan: None,
+        }
+    }
+
+    pub fn apply(&mut self, event: &CameraEvent) {
+        match *event {
+            CameraEvent::Shake { trauma } => {
+                self.trauma = (self.trauma + trauma).clamp(0.0, 1.0);
+            }
+            CameraEvent::ZoomPunch { amount, duration } => self.punches.push(ZoomPunch {
+                amount,
+                elapsed: 0.0,
+                duration,
+            }),
+            CameraEvent::PanTo { to, duration, hold } => {
+                self.pan = Some(Pan {
+                    to,
+                    elapsed: 0.0,
+                    duration,
+                    hold,
+                })
+            }
+        }
+    }
+
+    // Advances every effect by `dt` and returns the offset to add to `cam_pos` and the
+    // factor to multiply the scale by. Both are in camera space, like `cam_pos`.
+    pub fn update(&mut self, dt: f32, cam_pos: Position) -> (Position, f32) {
+        let mut offset = Position::new(0.0, 0.0);
+
+        if let Some(pan) = &mut self.pan {
+            let weight = pan.weight();
+            // The pan target is in world space, where Y points up.
+            offset.x += (pan.to.x - cam_pos.x) * weight;
+            offset.y += (-pan.to.y - cam_pos.y) * weight;
+            pan.elapsed += dt;
+            if pan.is_finished() {
+                self.pan = None;
+            }
+        }
+
+        if self.trauma > 0.0 {
+            // Squaring makes small knocks subtle and big hits violent.
+            let shake = self.trauma * self.trauma * self.max_shake;
+            offset.x += shake * (self.rng.rand_float() * 2.0 - 1.0);
+            offset.y += shake * (self.rng.rand_float() * 2.0 - 1.0);
+            self.trauma = (self.trauma - self.trauma_decay * dt).max(0.0);
+        }
+
+        let mut zoom = 1.0;
+        for punch in self.punches.iter_mut() {
+            let left = if punch.duration <= 0.0 {
+                0.0
+            } else {
+                1.0 - (punch.elapsed / punch.duration).min(1.0)
+            };
+            zoom *= 1.0 + punch.amount * left * left;
+            punch.elapsed += dt;
+        }
+        self.punches.retain(|p| p.elapsed < p.duration);
+
+        (offset, zoom)
+    }
+
+    pub fn is_idle(&self) -> bool {
+        self.trauma <= 0.0 && self.punches.is_empty() && self.pan.is_none()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DT: f32 = 1.0 / 60.0;
+
+    fn run(effects: &mut CameraEffects, ticks: usize) -> Vec<(Position, f32)> {
+        (0..ticks)
+            .map(|_| effects.update(DT, Position::new(0.0, 0.0)))
+            .collect()
+    }
+
+    #[test]
+    fn same_seed_shakes_the_same_way() {
+        let mut a = CameraEffects::new(9);
+        let mut b = CameraEffects::new(9);
+        a.apply(&CameraEvent::Shake { trauma: 0.8 });
+        b.apply(&CameraEvent::Shake { trauma: 0.8 });
+        assert_eq!(run(&mut a, 30), run(&mut b, 30));
+    }
+
+    #[test]
+    fn shake_wears_off() {
+        let mut effects = CameraEffects::new(1);
+        effects.apply(&CameraEvent::Shake { trauma: 2.0 });
+        assert_eq!(effects.trauma, 1.0);
+        let frames = run(&mut effects, 120);
+        assert_ne!(frames[0].0, Position::new(0.0, 0.0));
+        assert_eq!(frames[119], (Position::new(0.0, 0.0), 1.0));
+        assert!(effects.is_idle());
+    }
+
+    #[test]
+    fn zoom_punch_eases_back_to_normal() {
+        let mut effects = CameraEffects::new(1);
+        effects.apply(&CameraEvent::ZoomPunch {
+            amount: 0.2,
+            duration: 0.5,
+        });
+        let frames = run(&mut effects, 60);
+        assert!((frames[0].1 - 1.2).abs() < 1e-6);
+        assert!(frames.windows(2).all(|w| w[1].1 <= w[0].1));
+        assert_eq!(frames[59].1, 1.0);
+        assert!(effects.is_idle());
+    }
+
+    #[test]
+    fn pan_reaches_the_point_and_returns() {
+        let mut effects = CameraEffects::new(1);
+        effects.apply(&CameraEvent::PanTo {
+            to: Position::new(300.0, 100.0),
+            duration: 0.5,
+            hold: 0.5,
+        });
+        let frames = run(&mut effects, 120);
+        assert_eq!(frames[45].0, Position::new(300.0, -100.0));
+        assert_eq!(frames[119].0, Position::new(0.0, 0.0));
+        assert!(effects.is_idle());
+    }
+}
@@ -0,0 +1,412 @@
+use super::{
+    physics::{Aabb, Vec2},
+    Position, ALL_VIEW_LAYERS,
+};
+use ggez::graphics::{self, Color, Rect};
+use ggez::mint::Vector2;
+use ggez::Context;
+use specs::{Component, DenseVecStorage};
+
+mod effects;
+pub use self::effects::*;
+
+#[derive(Component)]
+pub struct Camera {
+    pub cur_pos: Position,
+    pub prev_pos: Option<Position>,
+    // Size of the view, which is always the size of `viewport`.
+    pub width: f32,
+    pub height: f32,
+    // Where on the screen the view is drawn.
+    pub viewport: Rect,
+    // Cameras are drawn from the lowest order to the highest, so later ones go on top.
+    pub order: i32,
+    // Only entities on one of these `ViewLayer`s are drawn.
+    pub layer_mask: u32,
+    // What the viewport is cleared to before drawing. `None` leaves it see-through.
+    pub background: Option<Color>,
+    pub cur_scale: Vector2<f32>,
+    pub prev_scale: Option<Vector2<f32>>,
+    // Area the view has to stay inside, in camera space (Y pointing down).
+    pub bounds: Option<Rect>,
+    // The scale `cur_scale` is easing towards, kept within `min_scale..=max_scale`.
+    pub target_scale: f32,
+    pub min_scale: f32,
+    pub max_scale: f32,
+    // How quickly the scale closes in on `target_scale`, per second.
+    pub zoom_smoothing: f32,
+    // View point whose world position stays put while zooming.
+    zoom_anchor: Option<Position>,
+    // Set by `CameraEffects` on top of `cur_pos` and `cur_scale` without changing them.
+    pub effect_offset: Position,
+    pub prev_effect_offset: Option<Position>,
+    pub effect_zoom: f32,
+    pub prev_effect_zoom: Option<f32>,
+}
+
+impl Camera {
+    pub fn new(cur_pos: Position, width: f32, height: f32, scale: f32) -> Self {
+        Self {
+            cur_pos,
+            prev_pos: None,
+            width,
+            height,
+            viewport: Rect::new(0.0, 0.0, width, height),
+            order: 0,
+            layer_mask: ALL_VIEW_LAYERS,
+            background: Some(graphics::BLACK),
+            cur_scale: Vector2 { x: scale, y: scale },
+            prev_scale: None,
+            bounds: None,
+            target_scale: scale,
+            min_scale: 0.1,
+            max_scale: 4.0,
+            zoom_smoothing: 12.0,
+            zoom_anchor: None,
+            effect_offset: Position::new(0.0, 0.0),
+            prev_effect_offset: None,
+            effect_zoom: 1.0,
+            prev_effect_zoom: None,
+        }
+    }
+
+    pub fn set_viewport(&mut self, viewport: Rect) {
+        self.viewport = viewport;
+        self.width = viewport.w;
+        self.height = viewport.h;
+    }
+
+    // Multiplies the target scale by `factor`. With an `anchor` in view coordinates, the
+    // world point under it stays there, otherwise the zoom is about the centre. Anchors
+    // only hold on cameras that aren't following a `Target`, since following recentres.
+    pub fn zoom_by(&mut self, factor: f32, anchor: Option<Position>) {
+        self.zoom_to(self.target_scale * factor, anchor);
+    }
+
+    pub fn zoom_to(&mut self, scale: f32, anchor: Option<Position>) {
+        self.target_scale = scale.max(self.min_scale).min(self.max_scale);
+        self.zoom_anchor = anchor;
+    }
+
+    // Jumps straight to `scale` without easing.
+    pub fn set_scale(&mut self, scale: f32) {
+        self.zoom_to(scale, None);
+        self.cur_scale = Vector2 {
+            x: self.target_scale,
+            y: self.target_scale,
+        };
+        self.prev_scale = None;
+    }
+
+    // Eases the scale a fraction `t` of the way to `target_scale`, remembering the old
+    // scale so the change can be interpolated.
+    pub fn update_zoom(&mut self, t: f32) {
+        const SETTLED: f32 = 1e-3;
+
+        let cur = self.cur_scale.x;
+        if (self.target_scale - cur).abs() <= SETTLED * cur {
+            self.cur_scale = Vector2 {
+                x: self.target_scale,
+                y: self.target_scale,
+            };
+            self.prev_scale = None;
+            self.zoom_anchor = None;
+            return;
+        }
+
+        // Zooming feels even when the scale changes by the same ratio each tick.
+        let new = cur * (self.target_scale / cur).powf(t.clamp(0.0, 1.0));
+        let anchor_world = self
+            .zoom_anchor
+            .map(|anchor| (anchor, self.to_world(anchor, self.cur_pos, self.cur_scale)));
+
+        self.prev_scale = Some(self.cur_scale);
+        self.cur_scale = Vector2 { x: new, y: new };
+
+        if let Some((anchor, world)) = anchor_world {
+            if self.prev_pos.is_none() {
+                self.prev_pos = Some(self.cur_pos);
+            }
+            self.cur_pos = Position::new(
+                world.x - (anchor.x - self.width / 2.0) / self.cur_scale.x,
+                -world.y - (anchor.y - self.height / 2.0) / self.cur_scale.y,
+            );
+        }
+    }
+
+    // Where the camera is looking, `alpha` of the way from the previous tick to this one,
+    // effects included.
+    pub fn pos_at(&self, alpha: f64) -> Position {
+        let base = lerp_pos(self.prev_pos, self.cur_pos, alpha);
+        let offset = lerp_pos(self.prev_effect_offset, self.effect_offset, alpha);
+        Position::new(base.x + offset.x, base.y + offset.y)
+    }
+
+    // The scale to draw with, `alpha` of the way from the previous tick to this one.
+    pub fn scale_at(&self, alpha: f64) -> Vector2<f32> {
+        let alpha = alpha as f32;
+        let zoom = match self.prev_effect_zoom {
+            Some(prev) => prev + (self.effect_zoom - prev) * alpha,
+            None => self.effect_zoom,
+        };
+        match self.prev_scale {
+            Some(prev) => Vector2 {
+                x: (prev.x + (self.cur_scale.x - prev.x) * alpha) * zoom,
+                y: (prev.y + (self.cur_scale.y - prev.y) * alpha) * zoom,
+            },
+            None => Vector2 {
+                x: self.cur_scale.x * zoom,
+                y: self.cur_scale.y * zoom,
+            },
+        }
+    }
+
+    // Maps a world position to screen coordinates. `alpha` interpolates the camera
+    // between ticks like the renderer does.
+    pub fn world_to_screen(&self, world: Position, alpha: f64) -> Position {
+        let view = self.world_to_view(world, alpha);
+        Position::new(view.x + self.viewport.x, view.y + self.viewport.y)
+    }
+
+    pub fn screen_to_world(&self, screen: Position, alpha: f64) -> Position {
+        self.view_to_world(self.screen_to_view(screen), alpha)
+    }
+
+    // Like `world_to_screen`, but relative to the top-left of the viewport.
+    pub fn world_to_view(&self, world: Position, alpha: f64) -> Position {
+        let (cam, scale) = (self.pos_at(alpha), self.scale_at(alpha));
+        Position::new(
+            (world.x - cam.x) * scale.x + self.width / 2.0,
+            (-world.y - cam.y) * scale.y + self.height / 2.0,
+        )
+    }
+
+    pub fn view_to_world(&self, view: Position, alpha: f64) -> Position {
+        self.to_world(view, self.pos_at(alpha), self.scale_at(alpha))
+    }
+
+    pub fn screen_to_view(&self, screen: Position) -> Position {
+        Position::new(screen.x - self.viewport.x, screen.y - self.viewport.y)
+    }
+
+    fn to_world(&self, screen: Position, cam: Position, scale: Vector2<f32>) -> Position {
+        Position::new(
+            cam.x + (screen.x - self.width / 2.0) / scale.x,
+            -(cam.y + (screen.y - self.height / 2.0) / scale.y),
+        )
+    }
+
+    // The part of the world the view shows, as drawn at `alpha`.
+    pub fn visible_bounds(&self, alpha: f64) -> Aabb {
+        let (cam, scale) = (self.pos_at(alpha), self.scale_at(alpha));
+        Aabb::new(
+            Vec2::new(cam.x, -cam.y),
+            Vec2::new(self.width / 2.0 / scale.x, self.height / 2.0 / scale.y),
+        )
+    }
+
+    // Half the size of the view in world units.
+    pub fn half_extents(&self) -> (f32, f32) {
+        (
+            self.width / 2.0 / self.cur_scale.x,
+            self.height / 2.0 / self.cur_scale.y,
+        )
+    }
+
+    // The closest position to `pos` that keeps the view inside `bounds`. Along an axis
+    // where the bounds are smaller than the view, the view is centred on them instead.
+    pub fn clamp(&self, pos: Position) -> Position {
+        match self.bounds {
+            Some(bounds) => self.clamp_within(pos, bounds),
+            None => pos,
+        }
+    }
+    // Like `clamp`, but against `bounds` rather than the camera's own.
+    pub fn clamp_within(&self, pos: Position, bounds: Rect) -> Position {
+        let (half_w, half_h) = self.half_extents();
+        Position::new(
+            clamp_axis(pos.x, bounds.x, bounds.w, half_w),
+            clamp_axis(pos.y, bounds.y, bounds.h, half_h),
+        )
+    }
+}
+
+fn lerp_pos(prev: Option<Position>, cur: Position, alpha: f64) -> Position {
+    match prev {
+        Some(prev) => {
+            let alpha = alpha as f32;
+            Position::new(
+                prev.x + (cur.x - prev.x) * alpha,
+                prev.y + (cur.y - prev.y) * alpha,
+            )
+        }
+        None => cur,
+    }
+}
+
+fn clamp_axis(pos: f32, start: f32, len: f32, half_view: f32) -> f32 {
+    if len <= half_view * 2.0 {
+        start + len / 2.0
+    } else {
+        pos.max(start + half_view).min(start + len - half_view)
+    }
+}
+
+// Window pixels, as given to the mouse events, to screen coordinates. They stop being
+// the same thing once the window is resized.
+pub fn window_to_screen(ctx: &Context, point: Position) -> Position {
+    let screen = graphics::screen_coordinates(ctx);
+    let (win_w, win_h) = graphics::drawable_size(ctx);
+    if win_w <= 0.0 || win_h <= 0.0 {
+        return point;
+    }
+    Position::new(
+        screen.x + point.x * screen.w / win_w,
+        screen.y + point.y * screen.h / win_h,
+    )
+}
+
+impl Default for Camera {
+    fn default() -> Self {
+        Self::new(Position::new(0.0, 0.0), 800., 600., 1.)
+    }
+}
+
+impl From<Rect> for Camera {
+    fn from(rect: Rect) -> Self {
+        Self::new(Position::new(rect.x, rect.y), rect.w, rect.h, 1.0)
+    }
+}
+
+impl From<&Rect> for Camera {
+    fn from(rect: &Rect) -> Self {
+        Self::new(Position::new(rect.x, rect.y), rect.w, rect.h, 1.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bounded_cam(scale: f32) -> Camera {
+        let mut cam = Camera::new(Position::new(0.0, 0.0), 800.0, 600.0, scale);
+        cam.bounds = Some(Rect::new(-1000.0, -500.0, 2000.0, 1000.0));
+        cam
+    }
+
+    #[test]
+    fn positions_inside_the_bounds_are_kept() {
+        let cam = bounded_cam(1.0);
+        assert_eq!(
+            cam.clamp(Position::new(100.0, -50.0)),
+            Position::new(100.0, -50.0)
+        );
+    }
+
+    #[test]
+    fn view_edges_stop_at_the_bounds() {
+        let cam = bounded_cam(1.0);
+        assert_eq!(
+            cam.clamp(Position::new(5000.0, -5000.0)),
+            Position::new(600.0, -200.0)
+        );
+    }
+
+    #[test]
+    fn zooming_in_lets_the_centre_get_closer_to_the_edges() {
+        let cam = bounded_cam(2.0);
+        assert_eq!(
+            cam.clamp(Position::new(5000.0, -5000.0)),
+            Position::new(800.0, -350.0)
+        );
+    }
+
+    #[test]
+    fn zoom_is_limited() {
+        let mut cam = Camera::default();
+        cam.zoom_by(100.0, None);
+        assert_eq!(cam.target_scale, cam.max_scale);
+        cam.zoom_by(0.0, None);
+        assert_eq!(cam.target_scale, cam.min_scale);
+    }
+
+    #[test]
+    fn zoom_settles_on_the_target() {
+        let mut cam = Camera::default();
+        cam.zoom_to(2.0, None);
+        for _ in 0..100 {
+            cam.update_zoom(0.2);
+        }
+        assert_eq!(cam.cur_scale.x, 2.0);
+        assert_eq!(cam.prev_scale, None);
+        assert_eq!(cam.cur_pos, Position::new(0.0, 0.0));
+    }
+
+    #[test]
+    fn zoom_keeps_the_point_under_the_anchor() {
+        let mut cam = Camera::default();
+        let anchor = Position::new(700.0, 100.0);
+        let before = cam.screen_to_world(anchor, 1.0);
+        cam.zoom_by(3.0, Some(anchor));
+        for _ in 0..10 {
+            cam.update_zoom(0.3);
+            let after = cam.screen_to_world(anchor, 1.0);
+            assert!((after.x - before.x).abs() < 1e-3);
+            assert!((after.y - before.y).abs() < 1e-3);
+        }
+        assert_ne!(cam.cur_pos, Position::new(0.0, 0.0));
+    }
+
+    #[test]
+    fn screen_and_world_round_trip() {
+        let mut cam = Camera::new(Position::new(120.0, -40.0), 800.0, 600.0, 1.5);
+        cam.prev_pos = Some(Position::new(100.0, -30.0));
+        cam.prev_scale = Some(Vector2 { x: 1.0, y: 1.0 });
+        let world = Position::new(-250.0, 75.0);
+        for &alpha in [0.0, 0.5, 1.0].iter() {
+            let back = cam.screen_to_world(cam.world_to_screen(world, alpha), alpha);
+            assert!((back.x - world.x).abs() < 1e-3);
+            assert!((back.y - world.y).abs() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn viewports_offset_screen_positions() {
+        let mut cam = Camera::new(Position::new(0.0, 0.0), 800.0, 600.0, 1.0);
+        cam.set_viewport(Rect::new(600.0, 20.0, 200.0, 100.0));
+        let world = Position::new(30.0, 10.0);
+        assert_eq!(cam.world_to_view(world, 1.0), Position::new(130.0, 40.0));
+        assert_eq!(cam.world_to_screen(world, 1.0), Position::new(730.0, 60.0));
+        assert_eq!(cam.screen_to_world(Position::new(730.0, 60.0), 1.0), world);
+    }
+
+    #[test]
+    fn visible_bounds_follow_position_and_scale() {
+        let cam = Camera::new(Position::new(100.0, -50.0), 800.0, 600.0, 2.0);
+        let bounds = cam.visible_bounds(1.0);
+        assert_eq!(bounds.min(), Vec2::new(-100.0, -100.0));
+        assert_eq!(bounds.max(), Vec2::new(300.0, 200.0));
+    }
+
+    #[test]
+    fn world_y_points_up_on_screen() {
+        let cam = Camera::new(Position::new(0.0, 0.0), 800.0, 600.0, 2.0);
+        assert_eq!(
+            cam.world_to_screen(Position::new(0.0, 0.0), 1.0),
+            Position::new(400.0, 300.0)
+        );
+        assert_eq!(
+            cam.world_to_screen(Position::new(10.0, 10.0), 1.0),
+            Position::new(420.0, 280.0)
+        );
+    }
+
+    #[test]
+    fn small_bounds_are_centred() {
+        let cam = bounded_cam(0.25);
+        assert_eq!(
+            cam.clamp(Position::new(5000.0, -5000.0)),
+            Position::new(0.0, 0.0)
+        );
+    }
+}
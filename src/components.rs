@@ -1,13 +1,15 @@
-use ggez::graphics::Drawable;
+use super::physics::{BodyType, CollisionLayer, Vec2};
+use ggez::graphics::{DrawParam, Image, Mesh};
 use specs::{Component, DenseVecStorage, Entity};
 use std::collections::{HashMap, HashSet};
 
+// `pos` is the centre of the door in world space.
 pub struct Door {
     pub to_room: Entity,
     pub pos: Position,
 }
 
-#[derive(PartialEq, Eq, Hash)]
+#[derive(PartialEq, Eq, Hash, Debug, Copy, Clone)]
 pub enum DoorType {
     Right,
     Left,
@@ -15,10 +17,36 @@ pub enum DoorType {
     Top,
     Bottom,
 }
+impl DoorType {
+    // The side a door leading back through this one sits on.
+    pub fn opposite(self) -> Self {
+        use DoorType::*;
+        match self {
+            Right => Left,
+            Left => Right,
+            Middle => Middle,
+            Top => Bottom,
+            Bottom => Top,
+        }
+    }
+}
 
 #[derive(Component)]
 pub struct Doors(pub HashMap<DoorType, Door>);
 
+// Trigger volume standing in for one of `room`'s doors.
+#[derive(Component)]
+pub struct DoorTrigger {
+    pub room: Entity,
+    pub door: DoorType,
+}
+
+// Moves an entity to `to` on the next tick, without interpolating the jump.
+#[derive(Component)]
+pub struct Teleport {
+    pub to: Position,
+}
+
 #[derive(PartialEq, Eq, Hash, Debug, Copy, Clone)]
 pub enum Direction {
     Right,
@@ -31,9 +59,119 @@ pub struct Facing {
     pub direction: Direction,
 }
 
+// Stops an entity's sprite from mirroring to match its `Facing`.
+#[derive(Component)]
+pub struct NoFlip;
+
 #[derive(Component)]
 pub struct IntentToMove(pub HashSet<Direction>);
 
+#[derive(Component)]
+pub struct IntentToJump;
+
+// Wants to go down through a trapdoor it is standing on.
+#[derive(Component)]
+pub struct IntentToDrop;
+
+#[derive(Component)]
+pub struct PlatformerController {
+    pub gravity: f32,
+    pub jump_speed: f32,
+    // Gravity multiplier while rising without holding jump, which makes short hops.
+    pub jump_cut: f32,
+    // Seconds after walking off a ledge during which a jump is still allowed.
+    pub coyote_time: f32,
+    // Seconds a jump pressed in the air is remembered for, in case we land.
+    pub jump_buffer: f32,
+    pub grounded: bool,
+    coyote_timer: f32,
+    buffer_timer: f32,
+    jump_held: bool,
+}
+impl PlatformerController {
+    pub fn new(gravity: f32, jump_speed: f32) -> Self {
+        Self {
+            gravity,
+            jump_speed,
+            jump_cut: 3.0,
+            coyote_time: 0.1,
+            jump_buffer: 0.12,
+            grounded: false,
+            coyote_timer: 0.0,
+            buffer_timer: 0.0,
+            jump_held: false,
+        }
+    }
+
+    // Advances the timers by `dt` and reports whether a jump should start this tick.
+    pub fn update_jump(&mut self, grounded: bool, jump_held: bool, dt: f32) -> bool {
+        self.grounded = grounded;
+        if grounded {
+            self.coyote_timer = self.coyote_time;
+        } else {
+            self.coyote_timer = (self.coyote_timer - dt).max(0.0);
+        }
+
+        if jump_held && !self.jump_held {
+            self.buffer_timer = self.jump_buffer;
+        } else {
+            self.buffer_timer = (self.buffer_timer - dt).max(0.0);
+        }
+        self.jump_held = jump_held;
+
+        if self.buffer_timer > 0.0 && self.coyote_timer > 0.0 {
+            self.buffer_timer = 0.0;
+            self.coyote_timer = 0.0;
+            self.grounded = false;
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn current_gravity(&self, vertical_speed: f32) -> f32 {
+        if vertical_speed > 0.0 && !self.jump_held {
+            self.gravity * self.jump_cut
+        } else {
+            self.gravity
+        }
+    }
+}
+
+// World units per second. Each axis is clamped to `max_speed`, and an axis with
+// no acceleration on it loses `friction` of its speed per second.
+#[derive(Component)]
+pub struct Velocity {
+    pub cur: Vec2,
+    pub max_speed: Vec2,
+    pub friction: f32,
+}
+impl Velocity {
+    pub fn new(max_speed: Vec2, friction: f32) -> Self {
+        Self {
+            cur: Vec2::new(0.0, 0.0),
+            max_speed,
+            friction,
+        }
+    }
+}
+
+// World units per second squared. `rate` is how hard an entity pushes itself
+// in the directions of its `IntentToMove`.
+#[derive(Component)]
+pub struct Acceleration {
+    pub cur: Vec2,
+    pub rate: f32,
+}
+impl Acceleration {
+    pub fn new(rate: f32) -> Self {
+        Self {
+            cur: Vec2::new(0.0, 0.0),
+            rate,
+        }
+    }
+}
+
 #[derive(Component)]
 pub struct Player;
 
@@ -60,16 +198,113 @@ impl From<Position> for ggez::mint::Point2<f32> {
         Self { x: pos.x, y: pos.y }
     }
 }
+impl From<Position> for Vec2 {
+    fn from(pos: Position) -> Self {
+        Self::new(pos.x, pos.y)
+    }
+}
+impl From<Vec2> for Position {
+    fn from(vec: Vec2) -> Self {
+        Self::new(vec.x, vec.y)
+    }
+}
 
+// Where an entity is in the world. `prev_pos` is where it was last tick, for drawing
+// in between ticks.
 #[derive(Component)]
-pub struct Renderable<D>
-where
-    D: Drawable + Send + Sync + 'static,
-{
-    pub drawable: D,
+pub struct Transform {
     pub cur_pos: Position,
     pub prev_pos: Option<Position>,
-    pub draw_param: Option<ggez::graphics::DrawParam>,
+}
+impl Transform {
+    pub fn new(pos: Position) -> Self {
+        Self {
+            cur_pos: pos,
+            prev_pos: None,
+        }
+    }
+}
+
+pub enum Visual {
+    Mesh(Mesh),
+    Image(Image),
+    // Turned into a `graphics::Text` when drawn, which can't be shared between threads.
+    Text { content: String, font_size: f32 },
+}
+impl From<Mesh> for Visual {
+    fn from(mesh: Mesh) -> Self {
+        Visual::Mesh(mesh)
+    }
+}
+impl From<Image> for Visual {
+    fn from(image: Image) -> Self {
+        Visual::Image(image)
+    }
+}
+
+// How an entity with a `Transform` and a `Size` is drawn.
+#[derive(Component)]
+pub struct Sprite {
+    pub visual: Visual,
+    pub draw_param: Option<DrawParam>,
+}
+impl Sprite {
+    pub fn new<V: Into<Visual>>(visual: V) -> Self {
+        Self {
+            visual: visual.into(),
+            draw_param: None,
+        }
+    }
+}
+
+// Optional mass and spin for dynamic colliders. Linear motion still lives in `Velocity`;
+// `orientation` is in radians, counter-clockwise.
+#[derive(Component)]
+pub struct RigidBody {
+    pub mass: f32,
+    pub inv_mass: f32,
+    pub inv_inertia: f32,
+    pub restitution: f32,
+    pub gravity: f32,
+    pub angular_velocity: f32,
+    pub angular_damping: f32,
+    pub orientation: f32,
+}
+impl RigidBody {
+    // Mass and inertia of a solid box of the given size. A mass of zero never moves.
+    pub fn new(mass: f32, restitution: f32, size: &Size) -> Self {
+        let (inv_mass, inv_inertia) = if mass > 0.0 {
+            let inertia = mass * (size.width * size.width + size.height * size.height) / 12.0;
+            (1.0 / mass, 1.0 / inertia)
+        } else {
+            (0.0, 0.0)
+        };
+        Self {
+            mass,
+            inv_mass,
+            inv_inertia,
+            restitution,
+            gravity: 0.0,
+            angular_velocity: 0.0,
+            angular_damping: 2.0,
+            orientation: 0.0,
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct Collider {
+    pub body: BodyType,
+}
+impl Collider {
+    pub fn new(body: BodyType) -> Self {
+        Self { body }
+    }
+}
+
+// Lives in physics so the collision world doesn't depend on the ECS.
+impl Component for CollisionLayer {
+    type Storage = DenseVecStorage<Self>;
 }
 
 #[derive(Component)]
@@ -83,6 +318,7 @@ impl Size {
     }
 }
 
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum RoomType {
     Boss,
     Start,
@@ -96,10 +332,184 @@ impl SpecialRoom {
     pub fn new(label: RoomType) -> Self {
         Self { label }
     }
+    pub fn label(&self) -> &RoomType {
+        &self.label
+    }
+}
+
+pub const ALL_VIEW_LAYERS: u32 = !0;
+
+// Which cameras draw an entity, matched against `Camera::layer_mask`. Entities without
+// one are on `WORLD`.
+#[derive(Component, Copy, Clone, Debug, PartialEq)]
+pub struct ViewLayer(pub u32);
+impl ViewLayer {
+    pub const WORLD: u32 = 1;
+    pub const UI: u32 = 1 << 1;
+
+    pub fn visible_to(layer: Option<&ViewLayer>, mask: u32) -> bool {
+        let layer = layer.map_or(Self::WORLD, |l| l.0);
+        layer & mask != 0
+    }
+}
+
+// Lower layers are drawn first. Entities without one are on `PROPS`. Within a layer,
+// entities with `y_sort` are drawn after the rest, the ones lower down in front.
+#[derive(Component, Copy, Clone, Debug, PartialEq)]
+pub struct RenderLayer {
+    pub z: i32,
+    pub y_sort: bool,
+}
+impl RenderLayer {
+    pub const BACKGROUND: i32 = -100;
+    pub const PROPS: i32 = 0;
+    pub const CHARACTERS: i32 = 100;
+    pub const FOREGROUND: i32 = 200;
+    pub const UI: i32 = 300;
+
+    pub fn new(z: i32) -> Self {
+        Self { z, y_sort: false }
+    }
+    pub fn y_sorted(z: i32) -> Self {
+        Self { z, y_sort: true }
+    }
+
+    // Where an entity whose box bottoms out at world height `bottom` goes in the draw
+    // order, smallest first.
+    pub fn sort_key(layer: Option<&RenderLayer>, bottom: f32) -> (i32, f32) {
+        match layer {
+            Some(l) if l.y_sort => (l.z, -bottom),
+            Some(l) => (l.z, f32::NEG_INFINITY),
+            None => (Self::PROPS, f32::NEG_INFINITY),
+        }
+    }
 }
 
+// Keeps a camera's view inside the active room.
+#[derive(Component)]
+pub struct ConfineToRoom;
+
+// Makes a camera follow `entity`. `offset` raises the point the camera looks at above
+// the target, and the target can move within `deadzone` (half extents) of that point
+// before the camera starts to catch up.
 #[derive(Component)]
 pub struct Target {
-    entity: Entity,
-    offset: f32,
+    pub entity: Entity,
+    pub offset: f32,
+    pub deadzone: Vec2,
+    // How far ahead of the target to look in the direction it is facing.
+    pub look_ahead: f32,
+    // How quickly the camera closes the gap, per second. Zero snaps straight to it.
+    pub smoothing: f32,
+    cur_look_ahead: f32,
+}
+impl Target {
+    pub fn new(entity: Entity, offset: f32) -> Self {
+        Self {
+            entity,
+            offset,
+            deadzone: Vec2::zero(),
+            look_ahead: 0.0,
+            smoothing: 0.0,
+            cur_look_ahead: 0.0,
+        }
+    }
+
+    // Eases the look-ahead towards the side `facing` points to and returns it.
+    pub fn update_look_ahead(&mut self, facing: Option<Direction>, t: f32) -> f32 {
+        let goal = match facing {
+            Some(Direction::Right) => self.look_ahead,
+            Some(Direction::Left) => -self.look_ahead,
+            _ => 0.0,
+        };
+        self.cur_look_ahead += (goal - self.cur_look_ahead) * t;
+        self.cur_look_ahead
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TICK: f32 = 0.02;
+
+    fn controller() -> PlatformerController {
+        PlatformerController::new(1000.0, 500.0)
+    }
+
+    #[test]
+    fn positions_convert_to_and_from_vectors() {
+        let pos = Position::new(3.5, -2.0);
+        assert_eq!(Vec2::from(pos), Vec2::new(3.5, -2.0));
+        assert_eq!(Position::from(Vec2::from(pos)), pos);
+    }
+
+    #[test]
+    fn jumps_on_press_while_grounded() {
+        let mut ctrl = controller();
+        assert!(!ctrl.update_jump(true, false, TICK));
+        assert!(ctrl.update_jump(true, true, TICK));
+        assert!(!ctrl.grounded);
+        // Holding the button does not jump again on landing.
+        assert!(!ctrl.update_jump(true, true, TICK));
+    }
+
+    #[test]
+    fn coyote_time_allows_late_jumps() {
+        let mut ctrl = controller();
+        ctrl.update_jump(true, false, TICK);
+        // Pressed 0.08s after walking off the ledge, inside the 0.1s window.
+        for _ in 0..3 {
+            assert!(!ctrl.update_jump(false, false, TICK));
+        }
+        assert!(ctrl.update_jump(false, true, TICK));
+    }
+
+    #[test]
+    fn coyote_time_runs_out() {
+        let mut ctrl = controller();
+        ctrl.update_jump(true, false, TICK);
+        for _ in 0..6 {
+            ctrl.update_jump(false, false, TICK);
+        }
+        assert!(!ctrl.update_jump(false, true, TICK));
+    }
+
+    #[test]
+    fn coyote_time_is_spent_by_the_jump() {
+        let mut ctrl = controller();
+        assert!(ctrl.update_jump(true, true, TICK));
+        ctrl.update_jump(false, false, TICK);
+        assert!(!ctrl.update_jump(false, true, TICK));
+    }
+
+    #[test]
+    fn buffered_presses_jump_on_landing() {
+        let mut ctrl = controller();
+        assert!(!ctrl.update_jump(false, true, TICK));
+        ctrl.update_jump(false, false, TICK);
+        ctrl.update_jump(false, false, TICK);
+        assert!(ctrl.update_jump(true, false, TICK));
+    }
+
+    #[test]
+    fn buffered_presses_expire() {
+        let mut ctrl = controller();
+        ctrl.update_jump(false, true, TICK);
+        // 0.14s later, past the 0.12s buffer.
+        for _ in 0..7 {
+            ctrl.update_jump(false, false, TICK);
+        }
+        assert!(!ctrl.update_jump(true, false, TICK));
+    }
+
+    #[test]
+    fn releasing_jump_early_cuts_the_rise() {
+        let mut ctrl = controller();
+        ctrl.update_jump(true, true, TICK);
+        assert_eq!(ctrl.current_gravity(100.0), 1000.0);
+        ctrl.update_jump(false, false, TICK);
+        assert_eq!(ctrl.current_gravity(100.0), 3000.0);
+        assert_eq!(ctrl.current_gravity(-100.0), 1000.0);
+    }
 }
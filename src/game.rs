@@ -1,13 +1,27 @@
 use super::{
     components::*,
-    systems::{MoveSystem, RenderSystem, StopMovingSystem},
-    Camera,
+    physics::{BodyType, CollisionLayer, CollisionWorld, Vec2},
+    rooms::{
+        validate_rooms, ActiveRoom, DungeonGenerator, GeneratedRoom, RoomTransition,
+        TransitionKind, DOOR_HEIGHT, DOOR_WIDTH,
+    },
+    screen::{ScalingPolicy, ScreenScaling},
+    systems::{
+        AnimationStateSystem, AnimationSystem, CameraBoundsSystem, CameraEffectsSystem,
+        CameraFollowSystem, CameraZoomSystem, ColliderSyncSystem, CollisionSystem, DoorSystem,
+        ImpulseSystem, IntegrateSystem, MoveSystem, PlatformerSystem, RenderStats, RenderSystem,
+        ResolveCollisionSystem, RigidBodySystem, RoomTransitionSystem, StopMovingSystem,
+        TeleportSystem,
+    },
+    window_to_screen, AnimTransition, Animation, AnimationEvents, AnimationStateMachine, Camera,
+    CameraEffects, CameraEvent, CameraEvents, Clip, Condition, PlayMode,
 };
 use ggez::{
+    conf,
     event::{self, EventHandler, KeyCode, KeyMods, MouseButton},
     graphics,
-    graphics::{Image, Mesh, Text},
-    timer, Context, GameResult,
+    graphics::{Rect, Text},
+    timer, Context, GameError, GameResult,
 };
 use specs::{Builder, Entities, Entity, Join, ReadStorage, RunNow, WorldExt, WriteStorage};
 use std::{
@@ -15,107 +29,386 @@ use std::{
     iter::FromIterator,
 };
 
+// Fixed for now so every run starts in the same dungeon.
+const DUNGEON_SEED: u64 = 0x5eed;
+
 pub struct Game {
     entity_manager: specs::World,
     main_cam: Entity,
     player: Entity,
+    // Off-screen targets the cameras render into, kept between frames.
+    canvases: HashMap<Entity, graphics::Canvas>,
+    scaling: ScreenScaling,
+    // The screen size the camera viewports are currently laid out for.
+    layout_size: (f32, f32),
 }
 
 impl Game {
     pub fn new(ctx: &mut ggez::Context) -> GameResult<Game> {
-        let mut entity_manager = specs::World::new();
-
-        entity_manager.register::<Renderable<Mesh>>();
-        entity_manager.register::<Renderable<Image>>();
-        entity_manager.register::<Doors>();
-        entity_manager.register::<Camera>();
-        entity_manager.register::<Player>();
-        entity_manager.register::<SpecialRoom>();
-        entity_manager.register::<IntentToMove>();
-        entity_manager.register::<Facing>();
-        entity_manager.register::<Size>();
+        let mut entity_manager = new_world();
 
         let screen = graphics::screen_coordinates(ctx);
         let camera = Camera::new(Position::new(0.0, 0.0), screen.w, screen.h, 1.0);
 
-        let main_cam = entity_manager.create_entity().with(camera).build();
-
-        let next_room = entity_manager.create_entity().build();
-        let mut doors = HashMap::new();
-        doors.insert(
-            DoorType::Right,
-            Door {
-                to_room: next_room,
-                pos: Position::new(0.0, screen.w - 50.0),
-            },
-        );
-        let (stw, sth) = (screen.w * 2.0, screen.h - 40.0);
-        let _start_room = entity_manager
+        let main_cam = entity_manager
             .create_entity()
-            .with(Size::new(stw, sth))
-            .with(Renderable {
-                drawable: graphics::MeshBuilder::new()
-                    .rectangle(
-                        graphics::DrawMode::fill(),
-                        graphics::Rect::new(0.0, 0.0, stw, sth),
-                        graphics::Color::new(0.0, 0.0, 1.0, 1.0),
-                    )
-                    .build(ctx)?,
-                cur_pos: Position::new(screen.w / 2.0 + 20.0, 0.0),
-                prev_pos: None,
-                draw_param: None,
-            })
-            //doors should probably be a seperate entity
-            .with(Doors(doors))
+            .with(camera)
+            .with(ConfineToRoom)
+            .with(CameraEffects::new(DUNGEON_SEED))
             .build();
 
+        // Rooms are at least as tall as the window, and their cells leave a screen's worth
+        // of space between neighbours so they never overlap.
+        let room_h = screen.h - 40.0;
+        let mut generator = DungeonGenerator::new(DUNGEON_SEED);
+        generator.room_count = 6;
+        generator.cell_size = Size::new(screen.w * 3.5, room_h * 2.0);
+        generator.min_room = Size::new(screen.w * 1.5, room_h);
+        generator.max_room = Size::new(screen.w * 2.5, room_h);
+        let dungeon = generator.generate(&mut entity_manager);
+        if let Err(errors) = validate_rooms(&entity_manager) {
+            let errors: Vec<String> = errors.iter().map(|e| e.to_string()).collect();
+            return Err(GameError::ResourceLoadError(format!(
+                "invalid dungeon: {}",
+                errors.join("; ")
+            )));
+        }
+        for room in dungeon.rooms.iter() {
+            let color = if room.entity == dungeon.start {
+                graphics::Color::new(0.0, 0.0, 1.0, 1.0)
+            } else if room.entity == dungeon.boss {
+                graphics::Color::new(0.5, 0.1, 0.1, 1.0)
+            } else {
+                graphics::Color::new(0.1, 0.5, 0.2, 1.0)
+            };
+            decorate_room(&mut entity_manager, ctx, room, color)?;
+        }
+        entity_manager.insert(ActiveRoom(Some(dungeon.start)));
+
+        let crate_size = 120.0;
+        for i in 0..3 {
+            let size = Size::new(crate_size, crate_size);
+            let mut body = RigidBody::new(1.0, 0.2, &size);
+            body.gravity = 2500.0;
+            entity_manager
+                .create_entity()
+                .with(Transform::new(Position::new(
+                    400.0 + i as f32 * 60.0,
+                    i as f32 * 200.0,
+                )))
+                .with(Sprite::new(
+                    graphics::MeshBuilder::new()
+                        .rectangle(
+                            graphics::DrawMode::fill(),
+                            graphics::Rect::new(0.0, 0.0, crate_size, crate_size),
+                            graphics::Color::new(0.6, 0.4, 0.2, 1.0),
+                        )
+                        .build(ctx)?,
+                ))
+                .with(body)
+                .with(size)
+                .with(Collider::new(BodyType::Dynamic))
+                .with(CollisionLayer::new(
+                    CollisionLayer::WORLD,
+                    CollisionLayer::ALL,
+                ))
+                .with(Velocity::new(Vec2::new(1200.0, 1200.0), 2.0))
+                .with(Acceleration::new(0.0))
+                .build();
+        }
+
         let (pw, ph) = (300.0, 400.0);
         let player = entity_manager
             .create_entity()
             .with(Player)
             .with(Size::new(pw, ph))
-            .with(Renderable {
-                drawable: graphics::Image::new(ctx, "/anim_tmp.png")?,
-
-                // drawable: graphics::MeshBuilder::new()
-                //     .rectangle(
-                //         graphics::DrawMode::fill(),
-                //         graphics::Rect::new(0.0, 0.0, pw, ph),
-                //         graphics::Color::new(0.0, 0.0, 0.0, 1.0),
-                //     )
-                //     .build(ctx)?,
-                // pos: Position::new(screen.w / 2.0 - 15.0, screen.h * 0.8 - 15.0),
-                cur_pos: Position::new(0.0, 0.0),
-                prev_pos: None,
-                draw_param: Some(
-                    graphics::DrawParam::new().src(graphics::Rect::new(0.0, 0.0, 0.5, 0.5)),
-                ),
+            .with(Transform::new(Position::new(0.0, 0.0)))
+            .with(Sprite {
+                visual: Visual::Image(graphics::Image::new(ctx, "/anim_tmp.png")?),
+                draw_param: None,
             })
+            .with(player_animation())
+            .with(player_animation_states())
+            .with(RenderLayer::y_sorted(RenderLayer::CHARACTERS))
             .with(Facing {
                 direction: Direction::Right,
             })
+            .with(Velocity::new(Vec2::new(292.0, 1200.0), 12.0))
+            .with(Acceleration::new(2400.0))
+            .with(Collider::new(BodyType::Dynamic))
+            .with(CollisionLayer::new(
+                CollisionLayer::PLAYER,
+                CollisionLayer::WORLD
+                    | CollisionLayer::ENEMY
+                    | CollisionLayer::PICKUP
+                    | CollisionLayer::DOOR,
+            ))
+            .with(PlatformerController::new(2500.0, 1000.0))
+            .build();
+
+        // A small overview in the top-right corner that keeps the player in the middle.
+        let (map_w, map_h) = (screen.w * 0.25, screen.h * 0.2);
+        let mut minimap = Camera::new(Position::new(0.0, 0.0), map_w, map_h, 0.04);
+        minimap.set_viewport(Rect::new(screen.w - map_w - 20.0, 20.0, map_w, map_h));
+        minimap.order = 1;
+        minimap.min_scale = 0.01;
+        minimap.max_scale = 0.2;
+        minimap.layer_mask = ViewLayer::WORLD;
+        minimap.background = Some(graphics::Color::new(0.05, 0.05, 0.05, 0.8));
+        entity_manager
+            .create_entity()
+            .with(minimap)
+            .with(Target::new(player, 0.0))
             .build();
 
+        let mut follow = Target::new(player, 100.0);
+        follow.deadzone = Vec2::new(60.0, 120.0);
+        follow.look_ahead = 150.0;
+        follow.smoothing = 6.0;
+        entity_manager
+            .write_storage::<Target>()
+            .insert(main_cam, follow)
+            .expect("Main camera follows the player");
+
         Ok(Self {
             entity_manager,
             main_cam,
             player,
+            canvases: HashMap::new(),
+            scaling: ScreenScaling::new(ScalingPolicy::Letterbox, screen.w, screen.h),
+            layout_size: (screen.w, screen.h),
         })
     }
+
+    pub fn set_scaling_policy(&mut self, ctx: &mut Context, policy: ScalingPolicy) {
+        self.scaling.policy = policy;
+        // Canvases pick their filter when they are made.
+        self.canvases.clear();
+        let (w, h) = graphics::drawable_size(ctx);
+        self.apply_scaling(ctx, w, h);
+    }
+
+    fn apply_scaling(&mut self, ctx: &mut Context, win_w: f32, win_h: f32) {
+        let rect = self.scaling.screen_rect(win_w, win_h);
+        graphics::set_screen_coordinates(ctx, rect).expect("Setting screen coordinates");
+
+        // Viewports keep their share of the screen when the layout size changes.
+        let layout = self.scaling.layout_size(win_w, win_h);
+        if layout != self.layout_size {
+            let (sx, sy) = (layout.0 / self.layout_size.0, layout.1 / self.layout_size.1);
+            let mut cams = self.entity_manager.write_storage::<Camera>();
+            for cam in (&mut cams).join() {
+                let v = cam.viewport;
+                cam.set_viewport(Rect::new(v.x * sx, v.y * sy, v.w * sx, v.h * sy));
+            }
+            self.layout_size = layout;
+        }
+    }
 }
 
-pub struct DeltaTime(f32);
-impl Default for DeltaTime {
-    fn default() -> Self {
-        Self(0.0)
+// The player's sheet is a 2x2 grid of frames.
+// A world with every component registered and every resource the systems read, so any
+// of them can run on it.
+pub fn new_world() -> specs::World {
+    let mut world = specs::World::new();
+    world.register::<Transform>();
+    world.register::<Sprite>();
+    world.register::<Animation>();
+    world.register::<AnimationStateMachine>();
+    world.register::<Doors>();
+    world.register::<Camera>();
+    world.register::<Player>();
+    world.register::<SpecialRoom>();
+    world.register::<IntentToMove>();
+    world.register::<Facing>();
+    world.register::<NoFlip>();
+    world.register::<RenderLayer>();
+    world.register::<Size>();
+    world.register::<Velocity>();
+    world.register::<Acceleration>();
+    world.register::<Collider>();
+    world.register::<RigidBody>();
+    world.register::<CollisionLayer>();
+    world.register::<PlatformerController>();
+    world.register::<IntentToJump>();
+    world.register::<IntentToDrop>();
+    world.register::<DoorTrigger>();
+    world.register::<Teleport>();
+    world.register::<Target>();
+    world.register::<ConfineToRoom>();
+    world.register::<CameraEffects>();
+    world.register::<ViewLayer>();
+    world.insert(DeltaTime::default());
+    world.insert(ActiveRoom::default());
+    world.insert(CollisionWorld::default());
+    world.insert(RoomTransition::default());
+    world.insert(CameraEvents::default());
+    world.insert(AnimationEvents::default());
+    world
+}
+
+fn player_animation() -> Animation {
+    let mut clips = HashMap::new();
+    clips.insert(
+        "idle".to_string(),
+        Clip::from_grid(2, 2, &[0, 1], 2.0, PlayMode::PingPong),
+    );
+    clips.insert(
+        "run".to_string(),
+        Clip::from_grid(2, 2, &[0, 1, 2, 3], 8.0, PlayMode::Loop),
+    );
+    clips.insert(
+        "jump".to_string(),
+        Clip::from_grid(2, 2, &[1, 2], 12.0, PlayMode::Once),
+    );
+    clips.insert(
+        "fall".to_string(),
+        Clip::from_grid(2, 2, &[3], 1.0, PlayMode::Loop),
+    );
+    clips.insert(
+        "attack".to_string(),
+        Clip::from_grid(2, 2, &[2, 3], 12.0, PlayMode::Once),
+    );
+    Animation::new(clips, "idle")
+}
+
+fn player_animation_states() -> AnimationStateMachine {
+    use Condition::*;
+    AnimationStateMachine::new(vec![
+        AnimTransition::new("attack", Triggered("attack".to_string()), 10),
+        AnimTransition::new("jump", Rising, 5),
+        AnimTransition::new("fall", Falling, 4),
+        AnimTransition::new("run", All(vec![Grounded, Moving]), 1),
+        AnimTransition::new("idle", All(vec![Grounded, Not(Box::new(Moving))]), 0),
+    ])
+}
+
+// Gives a generated room its background, walls and the trigger volumes of its doors.
+fn decorate_room(
+    entity_manager: &mut specs::World,
+    ctx: &mut Context,
+    room: &GeneratedRoom,
+    color: graphics::Color,
+) -> GameResult<()> {
+    let (size, doors) = {
+        let sizes = entity_manager.read_storage::<Size>();
+        let doors = entity_manager.read_storage::<Doors>();
+        let size = sizes.get(room.entity).expect("Generated room has a size");
+        let Doors(room_doors) = doors.get(room.entity).expect("Generated room has Doors");
+        let room_doors: Vec<(DoorType, Position)> = room_doors
+            .iter()
+            .map(|(door_type, door)| (*door_type, door.pos))
+            .collect();
+        (Size::new(size.width, size.height), room_doors)
+    };
+    let (w, h) = (size.width, size.height);
+
+    let background = graphics::MeshBuilder::new()
+        .rectangle(
+            graphics::DrawMode::fill(),
+            graphics::Rect::new(0.0, 0.0, w, h),
+            color,
+        )
+        .build(ctx)?;
+    entity_manager
+        .write_storage::<Transform>()
+        .insert(room.entity, Transform::new(room.pos))
+        .expect("Placing a room");
+    entity_manager
+        .write_storage::<Sprite>()
+        .insert(room.entity, Sprite::new(background))
+        .expect("Adding a room's background");
+    entity_manager
+        .write_storage::<RenderLayer>()
+        .insert(room.entity, RenderLayer::new(RenderLayer::BACKGROUND))
+        .expect("Putting a room's background behind everything");
+    build_walls(entity_manager, ctx, room.pos, w, h)?;
+
+    for (door, pos) in doors {
+        add_door_trigger(entity_manager, ctx, room.entity, door, pos)?;
+    }
+    Ok(())
+}
+
+fn add_door_trigger(
+    entity_manager: &mut specs::World,
+    ctx: &mut Context,
+    room: Entity,
+    door: DoorType,
+    pos: Position,
+) -> GameResult<()> {
+    entity_manager
+        .create_entity()
+        .with(DoorTrigger { room, door })
+        .with(Size::new(DOOR_WIDTH, DOOR_HEIGHT))
+        .with(Collider::new(BodyType::Static))
+        .with(CollisionLayer::trigger(
+            CollisionLayer::DOOR,
+            CollisionLayer::PLAYER,
+        ))
+        .with(Transform::new(pos))
+        .with(Sprite::new(
+            graphics::MeshBuilder::new()
+                .rectangle(
+                    graphics::DrawMode::fill(),
+                    graphics::Rect::new(0.0, 0.0, DOOR_WIDTH, DOOR_HEIGHT),
+                    graphics::Color::new(0.3, 0.2, 0.1, 1.0),
+                )
+                .build(ctx)?,
+        ))
+        .build();
+    Ok(())
+}
+
+// Surrounds a room with static colliders sitting just outside its edges.
+fn build_walls(
+    entity_manager: &mut specs::World,
+    ctx: &mut Context,
+    room_pos: Position,
+    room_w: f32,
+    room_h: f32,
+) -> GameResult<()> {
+    const THICKNESS: f32 = 20.0;
+    let (half_w, half_h) = (room_w / 2.0, room_h / 2.0);
+    let half_t = THICKNESS / 2.0;
+    let walls = [
+        (-half_w - half_t, 0.0, THICKNESS, room_h + THICKNESS * 2.0),
+        (half_w + half_t, 0.0, THICKNESS, room_h + THICKNESS * 2.0),
+        (0.0, half_h + half_t, room_w, THICKNESS),
+        (0.0, -half_h - half_t, room_w, THICKNESS),
+    ];
+
+    for (x, y, w, h) in walls.iter().cloned() {
+        entity_manager
+            .create_entity()
+            .with(Size::new(w, h))
+            .with(Collider::new(BodyType::Static))
+            .with(CollisionLayer::new(
+                CollisionLayer::WORLD,
+                CollisionLayer::ALL,
+            ))
+            .with(Transform::new(Position::new(
+                room_pos.x + x,
+                room_pos.y + y,
+            )))
+            .with(Sprite::new(
+                graphics::MeshBuilder::new()
+                    .rectangle(
+                        graphics::DrawMode::fill(),
+                        graphics::Rect::new(0.0, 0.0, w, h),
+                        graphics::Color::new(0.5, 0.5, 0.5, 1.0),
+                    )
+                    .build(ctx)?,
+            ))
+            .build();
     }
+    Ok(())
 }
-impl std::ops::Mul<&DeltaTime> for f32 {
-    type Output = Self;
 
-    fn mul(self, other: &DeltaTime) -> f32 {
-        let DeltaTime(dt) = other;
-        self * dt
+pub struct DeltaTime(pub f32);
+impl Default for DeltaTime {
+    fn default() -> Self {
+        Self(0.0)
     }
 }
 
@@ -124,33 +417,77 @@ impl EventHandler for Game {
         self.entity_manager.maintain();
 
         const DESIRED_FPS: u32 = 73;
+        // How fast holding a zoom key zooms, as a ratio of the scale per second.
+        const KEY_ZOOM_RATE: f32 = 1.5;
         while timer::check_update_time(ctx, DESIRED_FPS) {
             let keycodes = ggez::input::keyboard::pressed_keys(ctx);
 
-            // let delta_time = 1.0 / DESIRED_FPS as f32;
-            // self.entity_manager.insert(DeltaTime(delta_time));
+            let delta_time = 1.0 / DESIRED_FPS as f32;
+            self.entity_manager.insert(DeltaTime(delta_time));
 
-            let mut mv_mesh_sys = MoveSystem::<Mesh>::new();
-            mv_mesh_sys.run_now(&self.entity_manager);
+            let mut move_sys = MoveSystem;
+            move_sys.run_now(&self.entity_manager);
 
-            let mut mv_img_sys = MoveSystem::<Image>::new();
-            mv_img_sys.run_now(&self.entity_manager);
+            let mut platformer_sys = PlatformerSystem;
+            platformer_sys.run_now(&self.entity_manager);
+
+            let mut rigid_body_sys = RigidBodySystem;
+            rigid_body_sys.run_now(&self.entity_manager);
+
+            let mut int_sys = IntegrateSystem;
+            int_sys.run_now(&self.entity_manager);
+
+            self.entity_manager
+                .write_resource::<CollisionWorld>()
+                .clear();
+            let mut sync_sys = ColliderSyncSystem;
+            sync_sys.run_now(&self.entity_manager);
+
+            let mut collision_sys = CollisionSystem;
+            collision_sys.run_now(&self.entity_manager);
+
+            let mut impulse_sys = ImpulseSystem;
+            impulse_sys.run_now(&self.entity_manager);
+
+            let mut res_sys = ResolveCollisionSystem;
+            res_sys.run_now(&self.entity_manager);
+
+            let mut door_sys = DoorSystem::new(TransitionKind::Fade, 0.6);
+            door_sys.run_now(&self.entity_manager);
+
+            let mut room_trans_sys = RoomTransitionSystem::new(self.main_cam);
+            room_trans_sys.run_now(&self.entity_manager);
+
+            let mut tp_sys = TeleportSystem;
+            tp_sys.run_now(&self.entity_manager);
 
             let mut move_cam_system = super::systems::MoveCamSystem;
             move_cam_system.run_now(&self.entity_manager);
 
-            let mut stp_mesh_sys = StopMovingSystem::<Mesh>::new();
-            stp_mesh_sys.run_now(&self.entity_manager);
+            let mut zoom_sys = CameraZoomSystem;
+            zoom_sys.run_now(&self.entity_manager);
+
+            let mut cam_bounds_sys = CameraBoundsSystem;
+            cam_bounds_sys.run_now(&self.entity_manager);
+
+            let mut cam_effects_sys = CameraEffectsSystem;
+            cam_effects_sys.run_now(&self.entity_manager);
+
+            let mut stp_sys = StopMovingSystem;
+            stp_sys.run_now(&self.entity_manager);
 
-            let mut stp_img_sys = StopMovingSystem::<Image>::new();
-            stp_img_sys.run_now(&self.entity_manager);
+            let mut anim_state_sys = AnimationStateSystem;
+            anim_state_sys.run_now(&self.entity_manager);
+
+            let mut anim_sys = AnimationSystem;
+            anim_sys.run_now(&self.entity_manager);
 
             let (mut facings, mut int_moves, mut cams): (
                 WriteStorage<Facing>,
                 WriteStorage<IntentToMove>,
                 WriteStorage<Camera>,
             ) = self.entity_manager.system_data();
-            let mut cam = cams.get_mut(self.main_cam).unwrap();
+            let cam = cams.get_mut(self.main_cam).unwrap();
             //unofficial camera controlls for testing:
 
             fn add_move_dir_for_cam(
@@ -176,13 +513,18 @@ impl EventHandler for Game {
             }
             for key in keycodes.iter().cloned() {
                 if key == KeyCode::Key0 {
-                    cam.cur_scale.x = 1.0;
-                    cam.cur_scale.y = 1.0;
+                    cam.set_scale(1.0);
                     cam.cur_pos.x = 0.0;
                     cam.cur_pos.y = 0.0;
                 }
 
                 match key {
+                    KeyCode::Equals | KeyCode::Add => {
+                        cam.zoom_by((KEY_ZOOM_RATE * delta_time).exp(), None)
+                    }
+                    KeyCode::Minus | KeyCode::Subtract => {
+                        cam.zoom_by((-KEY_ZOOM_RATE * delta_time).exp(), None)
+                    }
                     KeyCode::A => {
                         add_move_dir_for_cam(Direction::Left, &mut int_moves, self.main_cam)
                     }
@@ -203,8 +545,8 @@ impl EventHandler for Game {
                 && !keycodes.contains(&KeyCode::D)
                 && !keycodes.contains(&KeyCode::S)
                 && !keycodes.contains(&KeyCode::W)
+                && int_moves.remove(self.main_cam).is_some()
             {
-                int_moves.remove(self.main_cam);
                 cam.prev_pos = None;
             }
 
@@ -249,6 +591,24 @@ impl EventHandler for Game {
                     }
                 }
             }
+
+            let mut jumps = self.entity_manager.write_storage::<IntentToJump>();
+            if keycodes.contains(&KeyCode::Up) || keycodes.contains(&KeyCode::Space) {
+                jumps
+                    .insert(self.player, IntentToJump)
+                    .expect("Player intent to jump");
+            } else {
+                jumps.remove(self.player);
+            }
+
+            let mut drops = self.entity_manager.write_storage::<IntentToDrop>();
+            if keycodes.contains(&KeyCode::Down) {
+                drops
+                    .insert(self.player, IntentToDrop)
+                    .expect("Player intent to drop");
+            } else {
+                drops.remove(self.player);
+            }
         }
         Ok(())
     }
@@ -259,17 +619,80 @@ impl EventHandler for Game {
         let tr = timer::remaining_update_time(ctx);
         let dt: f64 = 1.0 / 73.0;
         let alpha = timer::duration_to_f64(tr) / dt;
-        {
-            let mut mesh_render_system = RenderSystem::<Mesh>::new(ctx, alpha, self.main_cam);
-            mesh_render_system.run_now(&self.entity_manager);
+
+        let frame_time = timer::duration_to_f64(timer::delta(ctx)) as f32;
+        let mut follow_sys = CameraFollowSystem::new(alpha, frame_time);
+        follow_sys.run_now(&self.entity_manager);
+
+        let mut cams: Vec<(Entity, i32, Rect, Option<graphics::Color>)> = {
+            let (entities, cams): (Entities, ReadStorage<Camera>) =
+                self.entity_manager.system_data();
+            (&entities, &cams)
+                .join()
+                .map(|(e, cam)| (e, cam.order, cam.viewport, cam.background))
+                .collect()
+        };
+        cams.sort_by_key(|(_, order, _, _)| *order);
+        self.canvases
+            .retain(|cam, _| cams.iter().any(|(e, _, _, _)| e == cam));
+
+        self.entity_manager.insert(RenderStats::default());
+        let screen = graphics::screen_coordinates(ctx);
+        for (cam, _, viewport, background) in cams {
+            let (w, h) = (viewport.w.max(1.0) as u16, viewport.h.max(1.0) as u16);
+            let stale = match self.canvases.get(&cam) {
+                Some(canvas) => canvas.image().width() != w || canvas.image().height() != h,
+                None => true,
+            };
+            if stale {
+                let mut canvas = graphics::Canvas::new(ctx, w, h, conf::NumSamples::One)?;
+                if self.scaling.policy == ScalingPolicy::PixelPerfect {
+                    canvas.set_filter(graphics::FilterMode::Nearest);
+                }
+                self.canvases.insert(cam, canvas);
+            }
+            let canvas = &self.canvases[&cam];
+
+            // Draw the camera's view into its own canvas, which keeps it inside the viewport.
+            graphics::set_canvas(ctx, Some(canvas));
+            graphics::set_screen_coordinates(ctx, Rect::new(0.0, 0.0, viewport.w, viewport.h))?;
+            graphics::clear(
+                ctx,
+                background.unwrap_or_else(|| graphics::Color::new(0.0, 0.0, 0.0, 0.0)),
+            );
+            {
+                let mut render_system = RenderSystem::new(ctx, alpha, cam);
+                render_system.run_now(&self.entity_manager);
+            }
+            graphics::set_canvas(ctx, None);
+            graphics::set_screen_coordinates(ctx, screen)?;
+            graphics::draw(
+                ctx,
+                canvas,
+                graphics::DrawParam::default().dest(Position::new(viewport.x, viewport.y)),
+            )?;
         }
-        {
-            let mut img_render_system = RenderSystem::<Image>::new(ctx, alpha, self.main_cam);
-            img_render_system.run_now(&self.entity_manager);
+
+        let fade = match &self.entity_manager.read_resource::<RoomTransition>().0 {
+            Some(tr) => tr.fade_alpha(),
+            None => 0.0,
+        };
+        if fade > 0.0 {
+            let overlay = graphics::Mesh::new_rectangle(
+                ctx,
+                graphics::DrawMode::fill(),
+                graphics::screen_coordinates(ctx),
+                graphics::Color::new(0.0, 0.0, 0.0, fade),
+            )?;
+            graphics::draw(ctx, &overlay, graphics::DrawParam::default())?;
         }
 
         let fps = timer::fps(ctx);
-        let fps_display = Text::new(format!("FPS: {}", fps));
+        let stats = self.entity_manager.read_resource::<RenderStats>();
+        let fps_display = Text::new(format!(
+            "FPS: {}  drawn: {}  culled: {}  draw calls: {}",
+            fps, stats.drawn, stats.culled, stats.draw_calls
+        ));
         graphics::draw(
             ctx,
             &fps_display,
@@ -304,16 +727,75 @@ impl EventHandler for Game {
     fn mouse_motion_event(&mut self, _ctx: &mut Context, _x: f32, _y: f32, _dx: f32, _dy: f32) {}
     // The mouse was moved; it provides both absolute x and y coordinates in the window, and relative x and y coordinates compared to its last position.
 
-    fn mouse_wheel_event(&mut self, _ctx: &mut Context, _x: f32, _y: f32) {}
+    fn mouse_wheel_event(&mut self, ctx: &mut Context, _x: f32, y: f32) {
+        // Scale change for one notch of the wheel.
+        const WHEEL_ZOOM: f32 = 1.1;
+
+        let cursor = ggez::input::mouse::position(ctx);
+        let point = window_to_screen(ctx, Position::new(cursor.x, cursor.y));
+        let (mut cams, targets): (WriteStorage<Camera>, ReadStorage<Target>) =
+            self.entity_manager.system_data();
+        // Zoom whichever camera is drawn on top under the cursor.
+        let cam = (&mut cams, targets.maybe())
+            .join()
+            .filter(|(cam, _)| cam.viewport.contains(point))
+            .max_by_key(|(cam, _)| cam.order);
+        if let Some((cam, target)) = cam {
+            // A following camera would be pulled straight back onto its target, so it
+            // zooms about the centre instead of the cursor.
+            let anchor = match target {
+                Some(_) => None,
+                None => Some(cam.screen_to_view(point)),
+            };
+            cam.zoom_by(WHEEL_ZOOM.powf(y), anchor);
+        }
+    }
     // The mousewheel was scrolled, vertically (y, positive away from and negative toward the user) or horizontally (x, positive to the right and negative to the left).
 
     fn key_down_event(
         &mut self,
-        _ctx: &mut Context,
-        _keycode: KeyCode,
+        ctx: &mut Context,
+        keycode: KeyCode,
         _keymods: KeyMods,
-        _repeat: bool,
+        repeat: bool,
     ) {
+        if repeat {
+            return;
+        }
+        if keycode == KeyCode::F2 {
+            let next = match self.scaling.policy {
+                ScalingPolicy::Letterbox => ScalingPolicy::Expand,
+                ScalingPolicy::Expand => ScalingPolicy::PixelPerfect,
+                ScalingPolicy::PixelPerfect => ScalingPolicy::Letterbox,
+            };
+            self.set_scaling_policy(ctx, next);
+            return;
+        }
+        if keycode == KeyCode::X {
+            let mut machines = self.entity_manager.write_storage::<AnimationStateMachine>();
+            if let Some(machine) = machines.get_mut(self.player) {
+                machine.trigger("attack");
+            }
+            return;
+        }
+        //unofficial camera effect triggers for testing:
+        let event = match keycode {
+            KeyCode::K => CameraEvent::Shake { trauma: 0.5 },
+            KeyCode::Z => CameraEvent::ZoomPunch {
+                amount: 0.15,
+                duration: 0.3,
+            },
+            KeyCode::P => CameraEvent::PanTo {
+                to: Position::new(0.0, 0.0),
+                duration: 0.8,
+                hold: 1.0,
+            },
+            _ => return,
+        };
+        self.entity_manager
+            .write_resource::<CameraEvents>()
+            .0
+            .push(event);
     }
     // A keyboard button was pressed.
 
@@ -329,15 +811,17 @@ impl EventHandler for Game {
                 self.entity_manager.system_data();
             let f = facings.get(self.player).unwrap();
 
-            if keycode == KeyCode::Right {
-                if int_move.contains(self.player) && f.direction == Direction::Right {
-                    int_move.remove(self.player);
-                }
+            if keycode == KeyCode::Right
+                && int_move.contains(self.player)
+                && f.direction == Direction::Right
+            {
+                int_move.remove(self.player);
             }
-            if keycode == KeyCode::Left {
-                if int_move.contains(self.player) && f.direction == Direction::Left {
-                    int_move.remove(self.player);
-                }
+            if keycode == KeyCode::Left
+                && int_move.contains(self.player)
+                && f.direction == Direction::Left
+            {
+                int_move.remove(self.player);
             }
         }
 
@@ -368,4 +852,9 @@ impl EventHandler for Game {
     // A keyboard button was released.
 
     fn text_input_event(&mut self, _ctx: &mut Context, _character: char) {}
+
+    fn resize_event(&mut self, ctx: &mut Context, width: f32, height: f32) {
+        self.apply_scaling(ctx, width, height);
+    }
+    // The window was resized, to this size in logical pixels.
 }
@@ -1,8 +1,17 @@
+mod animation;
+pub use self::animation::{
+    AnimTransition, Animation, AnimationEvent, AnimationEventKind, AnimationEvents, AnimationInput,
+    AnimationStateMachine, Clip, Condition, PlayMode,
+};
 mod components;
 pub use self::components::*;
 mod camera;
-pub use self::camera::Camera;
+pub use self::camera::{window_to_screen, Camera, CameraEffects, CameraEvent, CameraEvents};
 mod game;
 pub use self::game::Game;
-mod physics;
+pub mod physics;
+mod rooms;
+pub use self::rooms::*;
+mod screen;
+pub use self::screen::{ScalingPolicy, ScreenScaling};
 mod systems;
@@ -0,0 +1,187 @@
+use super::{Aabb, Vec2};
+use std::collections::HashMap;
+
+// Boxes touching more cells than this are kept in a single list that every query
+// checks, instead of being copied into thousands of buckets.
+const MAX_CELLS_PER_BOX: i64 = 1024;
+
+// Uniform grid that buckets ids by every cell their box touches. Queries only
+// return candidates; callers still have to run the exact test.
+pub struct SpatialHash {
+    cell_size: f32,
+    cells: HashMap<(i32, i32), Vec<usize>>,
+    oversized: Vec<usize>,
+}
+impl SpatialHash {
+    pub fn new(cell_size: f32) -> Self {
+        Self {
+            cell_size,
+            cells: HashMap::new(),
+            oversized: Vec::new(),
+        }
+    }
+
+    pub fn clear(&mut self) {
+        // Buckets used since the last clear will most likely be refilled, so they keep
+        // their allocation. The ones that stayed empty are dropped.
+        self.cells.retain(|_, bucket| {
+            let used = !bucket.is_empty();
+            bucket.clear();
+            used
+        });
+        self.oversized.clear();
+    }
+
+    pub fn insert(&mut self, id: usize, aabb: &Aabb) {
+        if has_nan(aabb) {
+            // Overlaps nothing, so there is nothing to find it by.
+            return;
+        }
+        match self.cell_range(aabb) {
+            Some(((min_x, min_y), (max_x, max_y))) => {
+                for cx in min_x..=max_x {
+                    for cy in min_y..=max_y {
+                        self.cells.entry((cx, cy)).or_default().push(id);
+                    }
+                }
+            }
+            None => self.oversized.push(id),
+        }
+    }
+
+    // `aabb` has to be the box `id` was inserted with.
+    pub fn remove(&mut self, id: usize, aabb: &Aabb) {
+        if has_nan(aabb) {
+            return;
+        }
+        match self.cell_range(aabb) {
+            Some(((min_x, min_y), (max_x, max_y))) => {
+                for cx in min_x..=max_x {
+                    for cy in min_y..=max_y {
+                        if let Some(bucket) = self.cells.get_mut(&(cx, cy)) {
+                            bucket.retain(|&other| other != id);
+                        }
+                    }
+                }
+            }
+            None => self.oversized.retain(|&other| other != id),
+        }
+    }
+
+    // Sorted, deduplicated ids of everything sharing a cell with `aabb`.
+    pub fn query(&self, aabb: &Aabb) -> Vec<usize> {
+        if has_nan(aabb) {
+            return Vec::new();
+        }
+        let mut found = self.oversized.clone();
+        match self.cell_range(aabb) {
+            Some(((min_x, min_y), (max_x, max_y))) => {
+                for cx in min_x..=max_x {
+                    for cy in min_y..=max_y {
+                        if let Some(bucket) = self.cells.get(&(cx, cy)) {
+                            found.extend_from_slice(bucket);
+                        }
+                    }
+                }
+            }
+            // Cheaper to hand back everything than to walk that many cells.
+            None => {
+                for bucket in self.cells.values() {
+                    found.extend_from_slice(bucket);
+                }
+            }
+        }
+        found.sort_unstable();
+        found.dedup();
+        found
+    }
+
+    fn cell_of(&self, point: Vec2) -> (i32, i32) {
+        (
+            (point.x / self.cell_size).floor() as i32,
+            (point.y / self.cell_size).floor() as i32,
+        )
+    }
+
+    // The cells `aabb` touches, or None if there are too many of them. Infinite
+    // boxes land here too, since their cells saturate at the ends of the grid.
+    fn cell_range(&self, aabb: &Aabb) -> Option<((i32, i32), (i32, i32))> {
+        let (min, max) = (self.cell_of(aabb.min()), self.cell_of(aabb.max()));
+        let columns = i64::from(max.0) - i64::from(min.0) + 1;
+        let rows = i64::from(max.1) - i64::from(min.1) + 1;
+        if columns.saturating_mul(rows) > MAX_CELLS_PER_BOX {
+            None
+        } else {
+            Some((min, max))
+        }
+    }
+}
+
+fn has_nan(aabb: &Aabb) -> bool {
+    aabb.center.x.is_nan() || aabb.center.y.is_nan() || aabb.half.x.is_nan() || aabb.half.y.is_nan()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn square(x: f32, y: f32, size: f32) -> Aabb {
+        Aabb::from_size(Vec2::new(x, y), size, size)
+    }
+
+    #[test]
+    fn query_finds_boxes_in_shared_cells() {
+        let mut hash = SpatialHash::new(10.0);
+        hash.insert(0, &square(5.0, 5.0, 4.0));
+        hash.insert(1, &square(12.0, 5.0, 4.0));
+        hash.insert(2, &square(55.0, 55.0, 4.0));
+        // Straddles the first two cells.
+        hash.insert(3, &square(10.0, 5.0, 4.0));
+
+        assert_eq!(hash.query(&square(5.0, 5.0, 1.0)), vec![0, 3]);
+        assert_eq!(hash.query(&square(15.0, 5.0, 1.0)), vec![1, 3]);
+        assert_eq!(hash.query(&square(10.0, 5.0, 2.0)), vec![0, 1, 3]);
+        assert!(hash.query(&square(-50.0, 0.0, 1.0)).is_empty());
+    }
+
+    #[test]
+    fn removed_boxes_are_not_found() {
+        let mut hash = SpatialHash::new(10.0);
+        let aabb = square(10.0, 10.0, 4.0);
+        hash.insert(0, &aabb);
+        hash.insert(1, &aabb);
+        hash.remove(0, &aabb);
+        assert_eq!(hash.query(&aabb), vec![1]);
+    }
+
+    #[test]
+    fn clear_drops_buckets_left_empty() {
+        let mut hash = SpatialHash::new(10.0);
+        hash.insert(0, &square(5.0, 5.0, 1.0));
+        hash.clear();
+        assert_eq!(hash.cells.len(), 1);
+        assert!(hash.query(&square(5.0, 5.0, 1.0)).is_empty());
+        hash.clear();
+        assert!(hash.cells.is_empty());
+    }
+
+    #[test]
+    fn huge_boxes_are_found_without_bucketing() {
+        let mut hash = SpatialHash::new(10.0);
+        hash.insert(0, &square(0.0, 0.0, 1.0e9));
+        hash.insert(1, &Aabb::new(Vec2::zero(), Vec2::new(f32::INFINITY, 1.0)));
+        hash.insert(2, &square(505.0, 505.0, 1.0));
+        assert!(hash.cells.len() <= 1);
+        assert_eq!(hash.query(&square(-300.0, 40.0, 1.0)), vec![0, 1]);
+        assert_eq!(hash.query(&square(0.0, 0.0, 1.0e12)), vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn nan_boxes_are_ignored() {
+        let mut hash = SpatialHash::new(10.0);
+        hash.insert(0, &square(f32::NAN, 0.0, 1.0));
+        hash.insert(1, &square(0.0, 0.0, 1.0));
+        assert_eq!(hash.query(&square(0.0, 0.0, 1.0)), vec![1]);
+        assert!(hash.query(&square(0.0, f32::NAN, 1.0)).is_empty());
+    }
+}
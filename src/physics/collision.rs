@@ -0,0 +1,764 @@
+use super::{Mat22, QueryFilter, RayHit, SpatialHash, Vec2};
+use specs::Entity;
+use std::collections::{BTreeSet, HashMap};
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Aabb {
+    pub center: Vec2,
+    pub half: Vec2,
+}
+impl Aabb {
+    pub fn new(center: Vec2, half: Vec2) -> Self {
+        Self { center, half }
+    }
+    pub fn from_size(center: Vec2, width: f32, height: f32) -> Self {
+        Self {
+            center,
+            half: Vec2::new(width / 2.0, height / 2.0),
+        }
+    }
+    // Bounds of this box after turning it by `angle` around its centre.
+    pub fn rotated(&self, angle: f32) -> Self {
+        Self {
+            center: self.center,
+            half: Mat22::new_by_angle(angle).abs() * self.half,
+        }
+    }
+    pub fn min(&self) -> Vec2 {
+        self.center - self.half
+    }
+    pub fn max(&self) -> Vec2 {
+        self.center + self.half
+    }
+    pub fn closest_point(&self, point: Vec2) -> Vec2 {
+        let (min, max) = (self.min(), self.max());
+        Vec2::new(point.x.max(min.x).min(max.x), point.y.max(min.y).min(max.y))
+    }
+    pub fn overlaps(&self, other: &Aabb) -> bool {
+        (self.center.x - other.center.x).abs() < self.half.x + other.half.x
+            && (self.center.y - other.center.y).abs() < self.half.y + other.half.y
+    }
+    // How far `self` has to move, and in which direction, to stop overlapping `other`.
+    // The normal points away from `other`, along the axis of least penetration.
+    pub fn penetration(&self, other: &Aabb) -> Option<Manifold> {
+        let dx = other.center.x - self.center.x;
+        let px = self.half.x + other.half.x - dx.abs();
+        if px <= 0.0 {
+            return None;
+        }
+        let dy = other.center.y - self.center.y;
+        let py = self.half.y + other.half.y - dy.abs();
+        if py <= 0.0 {
+            return None;
+        }
+
+        if px < py {
+            let sign = if dx > 0.0 { -1.0 } else { 1.0 };
+            Some(Manifold {
+                normal: Vec2::new(sign, 0.0),
+                depth: px,
+                point: self.overlap_center(other),
+            })
+        } else {
+            let sign = if dy > 0.0 { -1.0 } else { 1.0 };
+            Some(Manifold {
+                normal: Vec2::new(0.0, sign),
+                depth: py,
+                point: self.overlap_center(other),
+            })
+        }
+    }
+    fn overlap_center(&self, other: &Aabb) -> Vec2 {
+        let (a_min, a_max) = (self.min(), self.max());
+        let (b_min, b_max) = (other.min(), other.max());
+        let min = Vec2::new(a_min.x.max(b_min.x), a_min.y.max(b_min.y));
+        let max = Vec2::new(a_max.x.min(b_max.x), a_max.y.min(b_max.y));
+        (min + max) / 2.0
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Manifold {
+    pub normal: Vec2,
+    pub depth: f32,
+    pub point: Vec2,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum BodyType {
+    Static,
+    Dynamic,
+}
+
+// `normal` points away from `other`, towards `entity`.
+#[derive(Copy, Clone, Debug)]
+pub struct Contact {
+    pub entity: Entity,
+    pub other: Entity,
+    pub normal: Vec2,
+    pub depth: f32,
+    pub point: Vec2,
+}
+impl Contact {
+    // The same contact seen from the other entity's side.
+    pub fn flipped(&self) -> Self {
+        Self {
+            entity: self.other,
+            other: self.entity,
+            normal: -self.normal,
+            depth: self.depth,
+            point: self.point,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TriggerEventKind {
+    Enter,
+    Stay,
+    Exit,
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct TriggerEvent {
+    pub kind: TriggerEventKind,
+    pub trigger: Entity,
+    pub other: Entity,
+}
+
+// Two colliders only interact when each one's `mask` has a bit of the other's `layer`.
+// Triggers report overlaps as events instead of blocking.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct CollisionLayer {
+    pub layer: u32,
+    pub mask: u32,
+    pub trigger: bool,
+}
+impl CollisionLayer {
+    pub const WORLD: u32 = 1;
+    pub const PLAYER: u32 = 1 << 1;
+    pub const ENEMY: u32 = 1 << 2;
+    pub const PROJECTILE: u32 = 1 << 3;
+    pub const PICKUP: u32 = 1 << 4;
+    pub const DOOR: u32 = 1 << 5;
+    pub const ALL: u32 = !0;
+
+    pub fn new(layer: u32, mask: u32) -> Self {
+        Self {
+            layer,
+            mask,
+            trigger: false,
+        }
+    }
+    pub fn trigger(layer: u32, mask: u32) -> Self {
+        Self {
+            layer,
+            mask,
+            trigger: true,
+        }
+    }
+    pub fn interacts_with(&self, other: &CollisionLayer) -> bool {
+        self.layer & other.mask != 0 && other.layer & self.mask != 0
+    }
+}
+impl Default for CollisionLayer {
+    fn default() -> Self {
+        Self::new(Self::ALL, Self::ALL)
+    }
+}
+
+struct Body {
+    entity: Entity,
+    aabb: Aabb,
+    body_type: BodyType,
+    layer: CollisionLayer,
+}
+
+const DEFAULT_CELL_SIZE: f32 = 256.0;
+
+// Snapshot of every collider for the current tick. It is cleared and refilled by the
+// sync systems, stepped once, and then read by whatever needs the results.
+pub struct CollisionWorld {
+    bodies: Vec<Body>,
+    index: HashMap<Entity, usize>,
+    broadphase: SpatialHash,
+    contacts: Vec<Contact>,
+    corrections: HashMap<Entity, Vec2>,
+    // (trigger, other) pairs, kept across ticks to tell enters from stays.
+    trigger_overlaps: BTreeSet<(Entity, Entity)>,
+    trigger_events: Vec<TriggerEvent>,
+}
+impl CollisionWorld {
+    pub fn new(cell_size: f32) -> Self {
+        Self {
+            bodies: Vec::new(),
+            index: HashMap::new(),
+            broadphase: SpatialHash::new(cell_size),
+            contacts: Vec::new(),
+            corrections: HashMap::new(),
+            trigger_overlaps: BTreeSet::new(),
+            trigger_events: Vec::new(),
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.bodies.clear();
+        self.index.clear();
+        self.broadphase.clear();
+        self.contacts.clear();
+        self.corrections.clear();
+    }
+
+    pub fn insert(
+        &mut self,
+        entity: Entity,
+        aabb: Aabb,
+        body_type: BodyType,
+        layer: CollisionLayer,
+    ) {
+        self.index.insert(entity, self.bodies.len());
+        self.broadphase.insert(self.bodies.len(), &aabb);
+        self.bodies.push(Body {
+            entity,
+            aabb,
+            body_type,
+            layer,
+        });
+    }
+
+    pub fn aabb(&self, entity: Entity) -> Option<Aabb> {
+        self.index.get(&entity).map(|&i| self.bodies[i].aabb)
+    }
+
+    // Finds every overlapping pair and pushes dynamic bodies out of static ones.
+    // Dynamic pairs are only reported, and so are overlaps with triggers, as events.
+    pub fn step(&mut self) {
+        self.contacts.clear();
+        self.corrections.clear();
+        let mut overlaps = BTreeSet::new();
+
+        for i in 0..self.bodies.len() {
+            if self.bodies[i].body_type != BodyType::Dynamic {
+                continue;
+            }
+            let mut candidates = self.broadphase.query(&self.bodies[i].aabb);
+            let mut next = 0;
+            while next < candidates.len() {
+                let j = candidates[next];
+                next += 1;
+                if i == j {
+                    continue;
+                }
+                let (a, b) = (&self.bodies[i], &self.bodies[j]);
+                if b.body_type == BodyType::Dynamic && j < i {
+                    // Dynamic pairs were already reported from the other side.
+                    continue;
+                }
+                if !a.layer.interacts_with(&b.layer) {
+                    continue;
+                }
+                let manifold = match a.aabb.penetration(&b.aabb) {
+                    Some(m) => m,
+                    None => continue,
+                };
+                if a.layer.trigger || b.layer.trigger {
+                    if a.layer.trigger {
+                        overlaps.insert((a.entity, b.entity));
+                    }
+                    if b.layer.trigger {
+                        overlaps.insert((b.entity, a.entity));
+                    }
+                    continue;
+                }
+                self.contacts.push(Contact {
+                    entity: a.entity,
+                    other: b.entity,
+                    normal: manifold.normal,
+                    depth: manifold.depth,
+                    point: manifold.point,
+                });
+
+                if b.body_type == BodyType::Static {
+                    self.move_body(i, manifold.normal * manifold.depth);
+                    // The push may have carried the body into cells it wasn't in before.
+                    for k in self.broadphase.query(&self.bodies[i].aabb) {
+                        if !candidates.contains(&k) {
+                            candidates.push(k);
+                        }
+                    }
+                }
+            }
+        }
+
+        self.update_trigger_events(overlaps);
+    }
+
+    fn update_trigger_events(&mut self, overlaps: BTreeSet<(Entity, Entity)>) {
+        use TriggerEventKind::*;
+        self.trigger_events.clear();
+        for &(trigger, other) in self.trigger_overlaps.difference(&overlaps) {
+            self.trigger_events.push(TriggerEvent {
+                kind: Exit,
+                trigger,
+                other,
+            });
+        }
+        for &(trigger, other) in overlaps.iter() {
+            let kind = if self.trigger_overlaps.contains(&(trigger, other)) {
+                Stay
+            } else {
+                Enter
+            };
+            self.trigger_events.push(TriggerEvent {
+                kind,
+                trigger,
+                other,
+            });
+        }
+        self.trigger_overlaps = overlaps;
+    }
+
+    // Enter, stay and exit events from the last step.
+    pub fn trigger_events(&self) -> &[TriggerEvent] {
+        &self.trigger_events
+    }
+
+    // Every entity whose box overlaps `rect`.
+    pub fn query_rect(&self, rect: &Aabb) -> Vec<Entity> {
+        self.broadphase
+            .query(rect)
+            .into_iter()
+            .map(|i| &self.bodies[i])
+            .filter(|b| b.aabb.overlaps(rect))
+            .map(|b| b.entity)
+            .collect()
+    }
+
+    // Every entity whose box comes within `radius` of `center`.
+    pub fn query_radius(&self, center: Vec2, radius: f32) -> Vec<Entity> {
+        let bounds = Aabb::new(center, Vec2::new(radius, radius));
+        self.broadphase
+            .query(&bounds)
+            .into_iter()
+            .map(|i| &self.bodies[i])
+            .filter(|b| b.aabb.closest_point(center).distance(center) <= radius)
+            .map(|b| b.entity)
+            .collect()
+    }
+
+    // First body hit by a ray from `origin` towards `dir`, within `max_distance`.
+    // Triggers are skipped unless the filter asks for them.
+    pub fn raycast(
+        &self,
+        origin: Vec2,
+        dir: Vec2,
+        max_distance: f32,
+        filter: QueryFilter,
+    ) -> Option<RayHit> {
+        let dir = dir.normalize();
+        let bounds = Aabb::new(origin, Vec2::zero()).swept_bounds(dir, max_distance);
+        self.closest_hit(&bounds, filter, |aabb| {
+            aabb.ray_intersection(origin, dir, max_distance)
+        })
+        .map(|(entity, distance, normal)| RayHit {
+            entity,
+            distance,
+            point: origin + dir * distance,
+            normal,
+        })
+    }
+
+    // First body hit when moving `shape` towards `dir`, within `max_distance`.
+    pub fn cast_box(
+        &self,
+        shape: &Aabb,
+        dir: Vec2,
+        max_distance: f32,
+        filter: QueryFilter,
+    ) -> Option<RayHit> {
+        let dir = dir.normalize();
+        let bounds = shape.swept_bounds(dir, max_distance);
+        self.closest_hit(&bounds, filter, |aabb| shape.sweep(dir, max_distance, aabb))
+            .map(|(entity, distance, normal)| RayHit {
+                entity,
+                distance,
+                point: shape.center + dir * distance,
+                normal,
+            })
+    }
+
+    fn closest_hit<F>(
+        &self,
+        bounds: &Aabb,
+        filter: QueryFilter,
+        test: F,
+    ) -> Option<(Entity, f32, Vec2)>
+    where
+        F: Fn(&Aabb) -> Option<(f32, Vec2)>,
+    {
+        self.broadphase
+            .query(bounds)
+            .into_iter()
+            .map(|i| &self.bodies[i])
+            .filter(|b| filter.accepts(b.entity, b.layer.layer, b.layer.trigger))
+            .filter_map(|b| test(&b.aabb).map(|(t, n)| (b.entity, t, n)))
+            .fold(None, |best: Option<(Entity, f32, Vec2)>, hit| match best {
+                Some(b) if b.1 <= hit.1 => Some(b),
+                _ => Some(hit),
+            })
+    }
+
+    pub fn contacts(&self) -> &[Contact] {
+        &self.contacts
+    }
+
+    // Every contact involving `entity`, with normals pointing towards it.
+    pub fn contacts_of(&self, entity: Entity) -> impl Iterator<Item = Contact> + '_ {
+        self.contacts.iter().filter_map(move |c| {
+            if c.entity == entity {
+                Some(*c)
+            } else if c.other == entity {
+                Some(c.flipped())
+            } else {
+                None
+            }
+        })
+    }
+
+    // Moves `entity` on top of whatever the step already pushed it by.
+    pub fn add_correction(&mut self, entity: Entity, delta: Vec2) {
+        if let Some(&i) = self.index.get(&entity) {
+            self.move_body(i, delta);
+        }
+    }
+
+    // Moves a body, keeping its broadphase cells in step, and records the push.
+    fn move_body(&mut self, i: usize, delta: Vec2) {
+        let body = &mut self.bodies[i];
+        self.broadphase.remove(i, &body.aabb);
+        body.aabb.center += delta;
+        self.broadphase.insert(i, &body.aabb);
+        *self
+            .corrections
+            .entry(body.entity)
+            .or_insert_with(Vec2::zero) += delta;
+    }
+
+    // Total distance `entity` was pushed during the last step, if it was pushed at all.
+    pub fn correction(&self, entity: Entity) -> Option<Vec2> {
+        self.corrections.get(&entity).copied()
+    }
+}
+
+impl Default for CollisionWorld {
+    fn default() -> Self {
+        Self::new(DEFAULT_CELL_SIZE)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use specs::{Builder, World, WorldExt};
+
+    fn entities(n: usize) -> Vec<Entity> {
+        let mut world = World::new();
+        (0..n).map(|_| world.create_entity().build()).collect()
+    }
+
+    fn square(x: f32, y: f32, size: f32) -> Aabb {
+        Aabb::from_size(Vec2::new(x, y), size, size)
+    }
+
+    #[test]
+    fn touching_boxes_do_not_overlap() {
+        let a = square(0.0, 0.0, 10.0);
+        assert!(a.overlaps(&square(9.0, 9.0, 10.0)));
+        assert!(!a.overlaps(&square(10.0, 0.0, 10.0)));
+        assert_eq!(a.penetration(&square(10.0, 0.0, 10.0)), None);
+        assert_eq!(a.penetration(&square(0.0, 20.0, 10.0)), None);
+    }
+
+    #[test]
+    fn penetration_picks_the_shallowest_axis() {
+        let a = square(0.0, 0.0, 10.0);
+        let m = a.penetration(&square(8.0, 1.0, 10.0)).unwrap();
+        assert_eq!(m.normal, Vec2::new(-1.0, 0.0));
+        assert_eq!(m.depth, 2.0);
+        assert_eq!(m.point, Vec2::new(4.0, 0.5));
+
+        let m = a.penetration(&square(1.0, -7.0, 10.0)).unwrap();
+        assert_eq!(m.normal, Vec2::new(0.0, 1.0));
+        assert_eq!(m.depth, 3.0);
+    }
+
+    #[test]
+    fn moving_by_the_mtv_separates_the_boxes() {
+        let (mut a, b) = (square(3.0, 2.0, 10.0), square(0.0, 0.0, 10.0));
+        let m = a.penetration(&b).unwrap();
+        a.center += m.normal * m.depth;
+        assert!(!a.overlaps(&b));
+    }
+
+    #[test]
+    fn step_pushes_dynamic_bodies_out_of_static_ones() {
+        let e = entities(2);
+        let mut world = CollisionWorld::default();
+        world.insert(
+            e[0],
+            square(0.0, 8.0, 10.0),
+            BodyType::Dynamic,
+            Default::default(),
+        );
+        world.insert(
+            e[1],
+            square(0.0, 0.0, 10.0),
+            BodyType::Static,
+            Default::default(),
+        );
+        world.step();
+
+        assert_eq!(world.aabb(e[0]).unwrap().center, Vec2::new(0.0, 10.0));
+        assert_eq!(world.aabb(e[1]).unwrap().center, Vec2::zero());
+        assert_eq!(world.correction(e[0]), Some(Vec2::new(0.0, 2.0)));
+        assert_eq!(world.correction(e[1]), None);
+
+        let contacts: Vec<_> = world.contacts_of(e[1]).collect();
+        assert_eq!(contacts.len(), 1);
+        assert_eq!(contacts[0].other, e[0]);
+        assert_eq!(contacts[0].normal, Vec2::new(0.0, -1.0));
+    }
+
+    #[test]
+    fn dynamic_pairs_are_reported_once_and_not_moved() {
+        let e = entities(2);
+        let mut world = CollisionWorld::default();
+        world.insert(
+            e[0],
+            square(0.0, 0.0, 10.0),
+            BodyType::Dynamic,
+            Default::default(),
+        );
+        world.insert(
+            e[1],
+            square(5.0, 0.0, 10.0),
+            BodyType::Dynamic,
+            Default::default(),
+        );
+        world.step();
+
+        assert_eq!(world.contacts().len(), 1);
+        assert_eq!(world.correction(e[0]), None);
+        assert_eq!(world.correction(e[1]), None);
+    }
+
+    #[test]
+    fn static_bodies_never_collide_with_each_other() {
+        let e = entities(2);
+        let mut world = CollisionWorld::default();
+        world.insert(
+            e[0],
+            square(0.0, 0.0, 10.0),
+            BodyType::Static,
+            Default::default(),
+        );
+        world.insert(
+            e[1],
+            square(5.0, 0.0, 10.0),
+            BodyType::Static,
+            Default::default(),
+        );
+        world.step();
+        assert!(world.contacts().is_empty());
+    }
+
+    #[test]
+    fn pushes_are_checked_against_the_cells_they_land_in() {
+        let e = entities(3);
+        let mut world = CollisionWorld::new(10.0);
+        world.insert(
+            e[0],
+            square(8.0, 5.0, 2.0),
+            BodyType::Dynamic,
+            Default::default(),
+        );
+        // A tall wall overlapping the body by 1.5 pushes it into the next cell over...
+        let wall = Aabb::new(Vec2::new(2.0, 5.0), Vec2::new(6.5, 50.0));
+        world.insert(e[1], wall, BodyType::Static, Default::default());
+        // ...where it lands in a ledge it could not have been paired with before.
+        let ledge = Aabb::new(Vec2::new(14.0, 5.0), Vec2::new(4.0, 1.0));
+        world.insert(e[2], ledge, BodyType::Static, Default::default());
+        world.step();
+
+        assert_eq!(world.contacts_of(e[2]).count(), 1);
+        assert!(!world.aabb(e[0]).unwrap().overlaps(&ledge));
+        assert_eq!(world.query_rect(&square(9.5, 5.0, 0.5)), vec![e[0]]);
+    }
+
+    #[test]
+    fn corrections_move_bodies_between_cells() {
+        let e = entities(1);
+        let mut world = CollisionWorld::new(10.0);
+        world.insert(
+            e[0],
+            square(5.0, 5.0, 2.0),
+            BodyType::Dynamic,
+            Default::default(),
+        );
+        world.add_correction(e[0], Vec2::new(30.0, 0.0));
+
+        assert!(world.query_rect(&square(5.0, 5.0, 2.0)).is_empty());
+        assert_eq!(world.query_rect(&square(35.0, 5.0, 2.0)), vec![e[0]]);
+        assert_eq!(world.correction(e[0]), Some(Vec2::new(30.0, 0.0)));
+    }
+
+    #[test]
+    fn rect_and_radius_queries_run_the_exact_test() {
+        let e = entities(2);
+        let mut world = CollisionWorld::new(100.0);
+        world.insert(
+            e[0],
+            square(0.0, 0.0, 10.0),
+            BodyType::Static,
+            Default::default(),
+        );
+        world.insert(
+            e[1],
+            square(20.0, 20.0, 10.0),
+            BodyType::Static,
+            Default::default(),
+        );
+
+        // Both share the query's cell, only the first actually overlaps it.
+        assert_eq!(world.query_rect(&square(4.0, 4.0, 4.0)), vec![e[0]]);
+        assert_eq!(world.query_radius(Vec2::new(7.0, 7.0), 6.0), vec![e[0]]);
+        assert_eq!(world.query_radius(Vec2::new(7.0, 7.0), 12.0).len(), 2);
+    }
+
+    #[test]
+    fn casts_see_through_triggers_unless_asked() {
+        let e = entities(2);
+        let mut world = CollisionWorld::default();
+        let trigger = CollisionLayer::trigger(CollisionLayer::DOOR, CollisionLayer::ALL);
+        world.insert(e[0], square(5.0, 0.0, 2.0), BodyType::Static, trigger);
+        world.insert(
+            e[1],
+            square(10.0, 0.0, 2.0),
+            BodyType::Static,
+            Default::default(),
+        );
+
+        let (origin, right) = (Vec2::zero(), Vec2::new(1.0, 0.0));
+        let hit = world.raycast(origin, right, 20.0, QueryFilter::default());
+        assert_eq!(hit.map(|h| h.entity), Some(e[1]));
+        let hit = world.raycast(origin, right, 20.0, QueryFilter::default().with_triggers());
+        assert_eq!(hit.map(|h| (h.entity, h.distance)), Some((e[0], 4.0)));
+
+        let shape = square(0.0, 0.0, 2.0);
+        let hit = world.cast_box(&shape, right, 20.0, QueryFilter::default());
+        assert_eq!(
+            hit.map(|h| (h.entity, h.point)),
+            Some((e[1], Vec2::new(8.0, 0.0)))
+        );
+        let filter = QueryFilter::default().excluding(e[1]);
+        assert!(world.cast_box(&shape, right, 20.0, filter).is_none());
+        let hit = world.cast_box(&shape, right, 20.0, filter.with_triggers());
+        assert_eq!(hit.map(|h| h.distance), Some(3.0));
+    }
+
+    // Refills the world like the sync systems do each tick, then steps it.
+    fn tick(
+        world: &mut CollisionWorld,
+        bodies: &[(Entity, Aabb, CollisionLayer)],
+    ) -> Vec<(TriggerEventKind, Entity, Entity)> {
+        world.clear();
+        for &(e, aabb, layer) in bodies {
+            world.insert(e, aabb, BodyType::Dynamic, layer);
+        }
+        world.step();
+        world
+            .trigger_events()
+            .iter()
+            .map(|ev| (ev.kind, ev.trigger, ev.other))
+            .collect()
+    }
+
+    #[test]
+    fn trigger_events_go_enter_stay_exit() {
+        use TriggerEventKind::*;
+        let e = entities(2);
+        let mut world = CollisionWorld::default();
+        let zone = (
+            e[0],
+            square(0.0, 0.0, 10.0),
+            CollisionLayer::trigger(CollisionLayer::ALL, CollisionLayer::ALL),
+        );
+        let player = |x| (e[1], square(x, 0.0, 2.0), CollisionLayer::default());
+
+        assert_eq!(tick(&mut world, &[zone, player(20.0)]), vec![]);
+        assert_eq!(
+            tick(&mut world, &[zone, player(4.0)]),
+            vec![(Enter, e[0], e[1])]
+        );
+        assert_eq!(
+            tick(&mut world, &[zone, player(0.0)]),
+            vec![(Stay, e[0], e[1])]
+        );
+        assert_eq!(
+            tick(&mut world, &[zone, player(20.0)]),
+            vec![(Exit, e[0], e[1])]
+        );
+        assert_eq!(tick(&mut world, &[zone, player(20.0)]), vec![]);
+        // Triggers never block or push.
+        assert!(world.contacts().is_empty());
+    }
+
+    #[test]
+    fn exits_come_before_enters_in_the_same_step() {
+        use TriggerEventKind::*;
+        let e = entities(3);
+        let mut world = CollisionWorld::default();
+        let trigger = CollisionLayer::trigger(CollisionLayer::ALL, CollisionLayer::ALL);
+        let left = (e[0], square(0.0, 0.0, 10.0), trigger);
+        let right = (e[1], square(20.0, 0.0, 10.0), trigger);
+        let player = |x| (e[2], square(x, 0.0, 2.0), CollisionLayer::default());
+
+        tick(&mut world, &[left, right, player(0.0)]);
+        assert_eq!(
+            tick(&mut world, &[left, right, player(20.0)]),
+            vec![(Exit, e[0], e[2]), (Enter, e[1], e[2])]
+        );
+    }
+
+    #[test]
+    fn removing_a_body_inside_a_trigger_exits_it() {
+        use TriggerEventKind::*;
+        let e = entities(2);
+        let mut world = CollisionWorld::default();
+        let zone = (
+            e[0],
+            square(0.0, 0.0, 10.0),
+            CollisionLayer::trigger(CollisionLayer::ALL, CollisionLayer::ALL),
+        );
+        let player = (e[1], square(0.0, 0.0, 2.0), CollisionLayer::default());
+
+        tick(&mut world, &[zone, player]);
+        assert_eq!(tick(&mut world, &[zone]), vec![(Exit, e[0], e[1])]);
+        // Coming back is a fresh enter.
+        assert_eq!(tick(&mut world, &[zone, player]), vec![(Enter, e[0], e[1])]);
+        assert_eq!(tick(&mut world, &[player]), vec![(Exit, e[0], e[1])]);
+    }
+
+    #[test]
+    fn layers_filter_trigger_overlaps() {
+        let e = entities(2);
+        let mut world = CollisionWorld::default();
+        let door = CollisionLayer::trigger(CollisionLayer::DOOR, CollisionLayer::PLAYER);
+        let enemy = CollisionLayer::new(CollisionLayer::ENEMY, CollisionLayer::ALL);
+        let bodies = [
+            (e[0], square(0.0, 0.0, 10.0), door),
+            (e[1], square(0.0, 0.0, 2.0), enemy),
+        ];
+        assert!(tick(&mut world, &bodies).is_empty());
+    }
+}
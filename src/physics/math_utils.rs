@@ -1,6 +1,7 @@
-use std::ops::{Add, AddAssign, Sub, SubAssign};
+use ggez::mint::{Point2, Vector2};
+use std::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Sub, SubAssign};
 
-#[derive(Copy, Clone, Debug, PartialEq)]
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
 pub struct Vec2 {
     pub x: f32,
     pub y: f32,
@@ -9,6 +10,9 @@ impl Vec2 {
     pub fn new(x: f32, y: f32) -> Self {
         Self { x, y }
     }
+    pub fn zero() -> Self {
+        Self { x: 0.0, y: 0.0 }
+    }
     pub fn set(&mut self, x: f32, y: f32) -> Self {
         self.x = x;
         self.y = y;
@@ -16,7 +20,42 @@ impl Vec2 {
         *self
     }
     pub fn len(&self) -> f32 {
-        (self.x * self.x + self.y * self.x).sqrt()
+        self.dot(*self).sqrt()
+    }
+    pub fn len_squared(&self) -> f32 {
+        self.dot(*self)
+    }
+    pub fn dot(&self, other: Vec2) -> f32 {
+        self.x * other.x + self.y * other.y
+    }
+    // z component of the 3D cross product, i.e. the signed area of the parallelogram.
+    pub fn cross(&self, other: Vec2) -> f32 {
+        self.x * other.y - self.y * other.x
+    }
+    // Unit vector in the same direction, or the zero vector if there is no direction.
+    pub fn normalize(&self) -> Self {
+        let len = self.len();
+        if len == 0.0 {
+            *self
+        } else {
+            *self / len
+        }
+    }
+    // Rotated a quarter turn counter-clockwise.
+    pub fn perp(&self) -> Self {
+        Self::new(-self.y, self.x)
+    }
+    pub fn lerp(&self, other: Vec2, t: f32) -> Self {
+        *self + (other - *self) * t
+    }
+    pub fn distance(&self, other: Vec2) -> f32 {
+        (other - *self).len()
+    }
+    pub fn rotate(&self, angle: f32) -> Self {
+        Mat22::new_by_angle(angle) * *self
+    }
+    pub fn abs(&self) -> Self {
+        Self::new(self.x.abs(), self.y.abs())
     }
 }
 impl Add for Vec2 {
@@ -53,16 +92,74 @@ impl SubAssign for Vec2 {
         }
     }
 }
+impl Mul<f32> for Vec2 {
+    type Output = Self;
+    fn mul(self, scalar: f32) -> Self {
+        Self::new(self.x * scalar, self.y * scalar)
+    }
+}
+impl Mul<Vec2> for f32 {
+    type Output = Vec2;
+    fn mul(self, vec: Vec2) -> Vec2 {
+        vec * self
+    }
+}
+impl MulAssign<f32> for Vec2 {
+    fn mul_assign(&mut self, scalar: f32) {
+        *self = *self * scalar
+    }
+}
+impl Div<f32> for Vec2 {
+    type Output = Self;
+    fn div(self, scalar: f32) -> Self {
+        Self::new(self.x / scalar, self.y / scalar)
+    }
+}
+impl DivAssign<f32> for Vec2 {
+    fn div_assign(&mut self, scalar: f32) {
+        *self = *self / scalar
+    }
+}
+impl Neg for Vec2 {
+    type Output = Self;
+    fn neg(self) -> Self {
+        Self::new(-self.x, -self.y)
+    }
+}
 
-#[derive(Debug)]
-pub struct mat22 {
-    col1: Vec2,
-    col2: Vec2,
+impl From<Point2<f32>> for Vec2 {
+    fn from(point: Point2<f32>) -> Self {
+        Self::new(point.x, point.y)
+    }
+}
+impl From<Vec2> for Point2<f32> {
+    fn from(vec: Vec2) -> Self {
+        Self { x: vec.x, y: vec.y }
+    }
+}
+impl From<Vector2<f32>> for Vec2 {
+    fn from(vector: Vector2<f32>) -> Self {
+        Self::new(vector.x, vector.y)
+    }
+}
+impl From<Vec2> for Vector2<f32> {
+    fn from(vec: Vec2) -> Self {
+        Self { x: vec.x, y: vec.y }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Mat22 {
+    pub col1: Vec2,
+    pub col2: Vec2,
 }
-impl mat22 {
+impl Mat22 {
     pub fn new(col1: Vec2, col2: Vec2) -> Self {
         Self { col1, col2 }
     }
+    pub fn identity() -> Self {
+        Self::new(Vec2::new(1.0, 0.0), Vec2::new(0.0, 1.0))
+    }
     pub fn new_by_angle(angle: f32) -> Self {
         let (s, c) = angle.sin_cos();
         let col1 = Vec2::new(c, s);
@@ -76,9 +173,15 @@ impl mat22 {
 
         Self { col1, col2 }
     }
+    pub fn abs(&self) -> Self {
+        Self::new(self.col1.abs(), self.col2.abs())
+    }
+    pub fn determinant(&self) -> f32 {
+        self.col1.cross(self.col2)
+    }
     pub fn invert(&self) -> Result<Self, String> {
         let (a, b, c, d) = (self.col1.x, self.col2.x, self.col1.y, self.col2.y);
-        let det = a * b - b * c;
+        let det = a * d - b * c;
 
         if det == 0.0 {
             Err(format!("Could not invert: {:?}", self))
@@ -90,3 +193,174 @@ impl mat22 {
         }
     }
 }
+impl Mul<Vec2> for Mat22 {
+    type Output = Vec2;
+    fn mul(self, vec: Vec2) -> Vec2 {
+        self.col1 * vec.x + self.col2 * vec.y
+    }
+}
+impl Mul for Mat22 {
+    type Output = Self;
+    fn mul(self, other: Mat22) -> Self {
+        Self::new(self * other.col1, self * other.col2)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    const EPS: f32 = 1e-3;
+
+    fn close(a: f32, b: f32) -> bool {
+        // Relative for big numbers, absolute near zero.
+        (a - b).abs() <= EPS * (1.0 + a.abs().max(b.abs()))
+    }
+
+    fn close_vec(a: Vec2, b: Vec2) -> bool {
+        close(a.x, b.x) && close(a.y, b.y)
+    }
+
+    fn close_mat(a: Mat22, b: Mat22) -> bool {
+        close_vec(a.col1, b.col1) && close_vec(a.col2, b.col2)
+    }
+
+    fn vec2() -> impl Strategy<Value = Vec2> {
+        (-1000.0f32..1000.0, -1000.0f32..1000.0).prop_map(|(x, y)| Vec2::new(x, y))
+    }
+
+    fn mat22() -> impl Strategy<Value = Mat22> {
+        (vec2(), vec2()).prop_map(|(c1, c2)| Mat22::new(c1 / 100.0, c2 / 100.0))
+    }
+
+    fn angle() -> impl Strategy<Value = f32> {
+        -10.0f32..10.0
+    }
+
+    #[test]
+    fn len_is_the_euclidean_length() {
+        assert_eq!(Vec2::new(3.0, 4.0).len(), 5.0);
+        assert_eq!(Vec2::new(0.0, -2.0).len(), 2.0);
+    }
+
+    #[test]
+    fn invert_uses_the_real_determinant() {
+        let m = Mat22::new(Vec2::new(2.0, 0.0), Vec2::new(1.0, 1.0));
+        assert_eq!(m.determinant(), 2.0);
+        assert!(close_mat(m * m.invert().unwrap(), Mat22::identity()));
+        assert!(Mat22::new(Vec2::new(1.0, 2.0), Vec2::new(2.0, 4.0))
+            .invert()
+            .is_err());
+    }
+
+    proptest! {
+        #[test]
+        fn add_then_sub_is_identity(a in vec2(), b in vec2()) {
+            prop_assert!(close_vec(a + b - b, a));
+        }
+
+        #[test]
+        fn scalar_mul_distributes(a in vec2(), b in vec2(), s in -10.0f32..10.0) {
+            prop_assert!(close_vec((a + b) * s, a * s + b * s));
+            prop_assert!(close_vec(s * a, a * s));
+        }
+
+        #[test]
+        fn div_undoes_mul(a in vec2(), s in 0.1f32..10.0) {
+            prop_assert!(close_vec(a * s / s, a));
+        }
+
+        #[test]
+        fn neg_is_additive_inverse(a in vec2()) {
+            prop_assert!(close_vec(a + -a, Vec2::zero()));
+        }
+
+        #[test]
+        fn dot_is_symmetric_and_matches_len(a in vec2(), b in vec2()) {
+            prop_assert!(close(a.dot(b), b.dot(a)));
+            prop_assert!(close(a.dot(a), a.len() * a.len()));
+        }
+
+        #[test]
+        fn cross_is_antisymmetric(a in vec2(), b in vec2()) {
+            prop_assert!(close(a.cross(b), -b.cross(a)));
+            prop_assert!(close(a.cross(a), 0.0));
+        }
+
+        #[test]
+        fn perp_is_orthogonal_and_same_length(a in vec2()) {
+            prop_assert!(close(a.dot(a.perp()), 0.0));
+            prop_assert!(close(a.perp().len(), a.len()));
+            prop_assert!(close(a.cross(a.perp()), a.len_squared()));
+        }
+
+        #[test]
+        fn normalize_gives_unit_length(a in vec2()) {
+            prop_assume!(a.len() > 1e-3);
+            prop_assert!(close(a.normalize().len(), 1.0));
+            prop_assert!(close(a.normalize().cross(a), 0.0));
+        }
+
+        #[test]
+        fn lerp_hits_both_ends(a in vec2(), b in vec2(), t in 0.0f32..1.0) {
+            prop_assert!(close_vec(a.lerp(b, 0.0), a));
+            prop_assert!(close_vec(a.lerp(b, 1.0), b));
+            prop_assert!(close(a.distance(a.lerp(b, t)), t * a.distance(b)));
+        }
+
+        #[test]
+        fn distance_is_symmetric_and_satisfies_triangle(a in vec2(), b in vec2(), c in vec2()) {
+            prop_assert!(close(a.distance(b), b.distance(a)));
+            prop_assert!(a.distance(c) <= a.distance(b) + b.distance(c) + EPS);
+        }
+
+        #[test]
+        fn rotation_preserves_length_and_composes(a in vec2(), r in angle(), s in angle()) {
+            prop_assert!(close(a.rotate(r).len(), a.len()));
+            prop_assert!(close_vec(a.rotate(r).rotate(s), a.rotate(r + s)));
+            prop_assert!(close_vec(a.rotate(r).rotate(-r), a));
+        }
+
+        #[test]
+        fn rotation_inverse_is_transpose(r in angle()) {
+            let m = Mat22::new_by_angle(r);
+            prop_assert!(close_mat(m.invert().unwrap(), m.transpose()));
+            prop_assert!(close_mat(m * m.transpose(), Mat22::identity()));
+        }
+
+        #[test]
+        fn mat_mul_is_associative_with_vectors(m in mat22(), n in mat22(), v in vec2()) {
+            // The products can cancel out, so the error is bounded by the size of the
+            // terms rather than by the result.
+            let bound = m.abs() * (n.abs() * v.abs());
+            let diff = (m * n) * v - m * (n * v);
+            prop_assert!(diff.x.abs() <= EPS * (1.0 + bound.x));
+            prop_assert!(diff.y.abs() <= EPS * (1.0 + bound.y));
+        }
+
+        #[test]
+        fn mat_mul_is_linear(m in mat22(), a in vec2(), b in vec2()) {
+            prop_assert!(close_vec(m * (a + b), m * a + m * b));
+        }
+
+        #[test]
+        fn inverse_undoes_matrix(m in mat22(), v in vec2()) {
+            // Keep to well conditioned matrices so rounding stays within tolerance.
+            prop_assume!(m.determinant().abs() > 0.1 * m.col1.len() * m.col2.len());
+            let inv = m.invert().unwrap();
+            prop_assert!(close_vec(inv * (m * v), v));
+        }
+
+        #[test]
+        fn transpose_of_product(m in mat22(), n in mat22()) {
+            prop_assert!(close_mat((m * n).transpose(), n.transpose() * m.transpose()));
+        }
+
+        #[test]
+        fn conversions_round_trip(a in vec2()) {
+            prop_assert_eq!(Vec2::from(Point2::from(a)), a);
+            prop_assert_eq!(Vec2::from(Vector2::from(a)), a);
+        }
+    }
+}
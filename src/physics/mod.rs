@@ -1,2 +1,10 @@
+mod broadphase;
+pub use self::broadphase::*;
+mod collision;
+pub use self::collision::*;
 mod math_utils;
-pub use self::math_utils::*;
\ No newline at end of file
+pub use self::math_utils::*;
+mod raycast;
+pub use self::raycast::*;
+mod solver;
+pub use self::solver::*;
@@ -0,0 +1,210 @@
+use super::{Aabb, CollisionLayer, Vec2};
+use specs::Entity;
+
+// Which bodies a query is allowed to hit. Triggers are see-through unless asked for.
+#[derive(Copy, Clone, Debug)]
+pub struct QueryFilter {
+    pub mask: u32,
+    pub exclude: Option<Entity>,
+    pub triggers: bool,
+}
+impl QueryFilter {
+    pub fn new(mask: u32) -> Self {
+        Self {
+            mask,
+            exclude: None,
+            triggers: false,
+        }
+    }
+    pub fn excluding(self, entity: Entity) -> Self {
+        Self {
+            exclude: Some(entity),
+            ..self
+        }
+    }
+    pub fn with_triggers(self) -> Self {
+        Self {
+            triggers: true,
+            ..self
+        }
+    }
+    pub fn accepts(&self, entity: Entity, layers: u32, trigger: bool) -> bool {
+        layers & self.mask != 0 && self.exclude != Some(entity) && (self.triggers || !trigger)
+    }
+}
+impl Default for QueryFilter {
+    fn default() -> Self {
+        Self::new(CollisionLayer::ALL)
+    }
+}
+
+// For shape casts `point` is where the centre of the cast box stops.
+#[derive(Copy, Clone, Debug)]
+pub struct RayHit {
+    pub entity: Entity,
+    pub distance: f32,
+    pub point: Vec2,
+    pub normal: Vec2,
+}
+
+impl Aabb {
+    // Slab test against a ray with a unit `dir`. Gives the distance along the ray and
+    // the normal of the face it enters through; a ray starting inside hits at 0
+    // facing back along itself.
+    pub fn ray_intersection(
+        &self,
+        origin: Vec2,
+        dir: Vec2,
+        max_distance: f32,
+    ) -> Option<(f32, Vec2)> {
+        let (min, max) = (self.min(), self.max());
+        let mut t_enter = 0.0;
+        let mut t_exit = max_distance;
+        let mut normal = -dir;
+
+        let axes = [
+            (origin.x, dir.x, min.x, max.x, Vec2::new(1.0, 0.0)),
+            (origin.y, dir.y, min.y, max.y, Vec2::new(0.0, 1.0)),
+        ];
+        for &(o, d, lo, hi, axis) in axes.iter() {
+            if d.abs() < f32::EPSILON {
+                if o < lo || o > hi {
+                    return None;
+                }
+                continue;
+            }
+            let inv = 1.0 / d;
+            let (mut t1, mut t2) = ((lo - o) * inv, (hi - o) * inv);
+            if t1 > t2 {
+                std::mem::swap(&mut t1, &mut t2);
+            }
+            if t1 > t_enter {
+                t_enter = t1;
+                let sign = if d > 0.0 { -1.0 } else { 1.0 };
+                normal = axis * sign;
+            }
+            t_exit = t2.min(t_exit);
+            if t_enter > t_exit {
+                return None;
+            }
+        }
+
+        Some((t_enter, normal))
+    }
+
+    // Sweeps `self` along `dir` against a stationary `other`, which is a ray cast
+    // against `other` grown by our half extents.
+    pub fn sweep(&self, dir: Vec2, max_distance: f32, other: &Aabb) -> Option<(f32, Vec2)> {
+        let grown = Aabb::new(other.center, other.half + self.half);
+        grown.ray_intersection(self.center, dir, max_distance)
+    }
+
+    // Smallest box holding `self` at every point along a cast.
+    pub fn swept_bounds(&self, dir: Vec2, distance: f32) -> Aabb {
+        let end = self.center + dir * distance;
+        let min = Vec2::new(
+            self.center.x.min(end.x) - self.half.x,
+            self.center.y.min(end.y) - self.half.y,
+        );
+        let max = Vec2::new(
+            self.center.x.max(end.x) + self.half.x,
+            self.center.y.max(end.y) + self.half.y,
+        );
+        let half = (max - min) / 2.0;
+        Aabb::new(min + half, half)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unit_box() -> Aabb {
+        Aabb::new(Vec2::zero(), Vec2::new(1.0, 1.0))
+    }
+
+    #[test]
+    fn rays_hit_the_face_they_enter_through() {
+        let hit = unit_box().ray_intersection(Vec2::new(-5.0, 0.5), Vec2::new(1.0, 0.0), 10.0);
+        assert_eq!(hit, Some((4.0, Vec2::new(-1.0, 0.0))));
+
+        let dir = Vec2::new(-1.0, -1.0).normalize();
+        let (t, normal) = unit_box()
+            .ray_intersection(Vec2::new(3.0, 4.0), dir, 10.0)
+            .unwrap();
+        assert_eq!(normal, Vec2::new(0.0, 1.0));
+        assert!((t - 3.0 * 2f32.sqrt()).abs() < 1e-5);
+    }
+
+    #[test]
+    fn rays_miss_when_short_aimed_away_or_off_to_the_side() {
+        let b = unit_box();
+        let right = Vec2::new(1.0, 0.0);
+        assert_eq!(b.ray_intersection(Vec2::new(-5.0, 0.0), right, 3.0), None);
+        assert_eq!(b.ray_intersection(Vec2::new(5.0, 0.0), right, 10.0), None);
+        assert_eq!(b.ray_intersection(Vec2::new(-5.0, 2.0), right, 10.0), None);
+    }
+
+    #[test]
+    fn rays_starting_inside_hit_at_zero_facing_back() {
+        let dir = Vec2::new(0.0, 1.0);
+        let hit = unit_box().ray_intersection(Vec2::new(0.2, 0.3), dir, 10.0);
+        assert_eq!(hit, Some((0.0, -dir)));
+    }
+
+    #[test]
+    fn zero_length_rays_only_hit_from_inside() {
+        let b = unit_box();
+        let right = Vec2::new(1.0, 0.0);
+        assert_eq!(b.ray_intersection(Vec2::new(-2.0, 0.0), right, 0.0), None);
+        assert_eq!(
+            b.ray_intersection(Vec2::zero(), right, 0.0).map(|h| h.0),
+            Some(0.0)
+        );
+        // No direction at all.
+        assert_eq!(
+            b.ray_intersection(Vec2::new(-2.0, 0.0), Vec2::zero(), 10.0),
+            None
+        );
+        assert_eq!(
+            b.ray_intersection(Vec2::zero(), Vec2::zero(), 10.0)
+                .map(|h| h.0),
+            Some(0.0)
+        );
+    }
+
+    #[test]
+    fn axis_parallel_rays_along_an_edge_graze_it() {
+        let b = unit_box();
+        let right = Vec2::new(1.0, 0.0);
+        let hit = b.ray_intersection(Vec2::new(-5.0, 1.0), right, 10.0);
+        assert_eq!(hit, Some((4.0, Vec2::new(-1.0, 0.0))));
+        assert_eq!(
+            b.ray_intersection(Vec2::new(-5.0, 1.001), right, 10.0),
+            None
+        );
+        let up = Vec2::new(0.0, 1.0);
+        assert_eq!(b.ray_intersection(Vec2::new(-1.001, -5.0), up, 10.0), None);
+    }
+
+    #[test]
+    fn sweeps_stop_where_the_boxes_touch() {
+        let mover = Aabb::new(Vec2::new(-5.0, 0.0), Vec2::new(0.5, 0.5));
+        let right = Vec2::new(1.0, 0.0);
+        let hit = mover.sweep(right, 10.0, &unit_box());
+        assert_eq!(hit, Some((3.5, Vec2::new(-1.0, 0.0))));
+        // Passes just above the box.
+        let above = Aabb::new(Vec2::new(-5.0, 1.6), Vec2::new(0.5, 0.5));
+        assert_eq!(above.sweep(right, 10.0, &unit_box()), None);
+        assert_eq!(mover.sweep(right, 3.0, &unit_box()), None);
+    }
+
+    #[test]
+    fn swept_bounds_cover_both_ends_of_the_cast() {
+        let b = Aabb::new(Vec2::new(1.0, 1.0), Vec2::new(1.0, 2.0));
+        let swept = b.swept_bounds(Vec2::new(-1.0, 0.0), 4.0);
+        assert_eq!(swept.min(), Vec2::new(-4.0, -1.0));
+        assert_eq!(swept.max(), Vec2::new(2.0, 3.0));
+        assert_eq!(b.swept_bounds(Vec2::new(0.0, 1.0), 0.0), b);
+    }
+}
@@ -0,0 +1,176 @@
+use super::Vec2;
+
+// Share of the remaining overlap removed each step, and how much overlap is
+// tolerated before correcting at all. Both keep resting stacks from jittering.
+const CORRECTION_PERCENT: f32 = 0.8;
+const CORRECTION_SLOP: f32 = 0.5;
+
+// What the solver needs to know about one side of a contact. Zero inverse mass and
+// inertia make a body immovable, which is how static and kinematic bodies are fed in.
+#[derive(Copy, Clone, Debug)]
+pub struct BodyState {
+    pub center: Vec2,
+    pub velocity: Vec2,
+    pub angular_velocity: f32,
+    pub inv_mass: f32,
+    pub inv_inertia: f32,
+    pub restitution: f32,
+}
+impl BodyState {
+    pub fn immovable(center: Vec2, velocity: Vec2) -> Self {
+        Self {
+            center,
+            velocity,
+            angular_velocity: 0.0,
+            inv_mass: 0.0,
+            inv_inertia: 0.0,
+            restitution: 0.0,
+        }
+    }
+
+    fn point_velocity(&self, r: Vec2) -> Vec2 {
+        self.velocity + r.perp() * self.angular_velocity
+    }
+
+    fn apply_impulse(&mut self, impulse: Vec2, r: Vec2) {
+        self.velocity += impulse * self.inv_mass;
+        self.angular_velocity += r.cross(impulse) * self.inv_inertia;
+    }
+}
+
+// Applies equal and opposite impulses at `point` so that `a` and `b` stop closing in
+// along `normal`, which points from `b` towards `a`. Returns the impulse magnitude.
+pub fn apply_contact_impulse(
+    a: &mut BodyState,
+    b: &mut BodyState,
+    normal: Vec2,
+    point: Vec2,
+) -> f32 {
+    let (ra, rb) = (point - a.center, point - b.center);
+    let closing = (a.point_velocity(ra) - b.point_velocity(rb)).dot(normal);
+    if closing >= 0.0 {
+        return 0.0;
+    }
+
+    let (ra_n, rb_n) = (ra.cross(normal), rb.cross(normal));
+    let denom = a.inv_mass + b.inv_mass + ra_n * ra_n * a.inv_inertia + rb_n * rb_n * b.inv_inertia;
+    if denom == 0.0 {
+        return 0.0;
+    }
+
+    let restitution = a.restitution.max(b.restitution);
+    let j = -(1.0 + restitution) * closing / denom;
+    a.apply_impulse(normal * j, ra);
+    b.apply_impulse(-normal * j, rb);
+    j
+}
+
+// How far to move `a` and `b` to undo an overlap of `depth`, split by inverse mass.
+pub fn positional_correction(
+    a: &BodyState,
+    b: &BodyState,
+    normal: Vec2,
+    depth: f32,
+) -> (Vec2, Vec2) {
+    let total = a.inv_mass + b.inv_mass;
+    if total == 0.0 {
+        return (Vec2::zero(), Vec2::zero());
+    }
+    let correction = normal * ((depth - CORRECTION_SLOP).max(0.0) / total * CORRECTION_PERCENT);
+    (correction * a.inv_mass, -correction * b.inv_mass)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn body(x: f32, vx: f32, mass: f32, restitution: f32) -> BodyState {
+        BodyState {
+            center: Vec2::new(x, 0.0),
+            velocity: Vec2::new(vx, 0.0),
+            angular_velocity: 0.0,
+            inv_mass: 1.0 / mass,
+            inv_inertia: 0.0,
+            restitution,
+        }
+    }
+
+    // `b` on the left moving right into `a`, so the normal points from `b` to `a`.
+    const NORMAL: Vec2 = Vec2 { x: 1.0, y: 0.0 };
+
+    #[test]
+    fn elastic_collisions_swap_equal_velocities() {
+        let (mut a, mut b) = (body(1.0, -2.0, 1.0, 1.0), body(-1.0, 3.0, 1.0, 1.0));
+        let j = apply_contact_impulse(&mut a, &mut b, NORMAL, Vec2::zero());
+        assert_eq!(j, 5.0);
+        assert_eq!(a.velocity, Vec2::new(3.0, 0.0));
+        assert_eq!(b.velocity, Vec2::new(-2.0, 0.0));
+    }
+
+    #[test]
+    fn inelastic_collisions_move_together_and_keep_momentum() {
+        let (mut a, mut b) = (body(1.0, 0.0, 3.0, 0.0), body(-1.0, 4.0, 1.0, 0.0));
+        apply_contact_impulse(&mut a, &mut b, NORMAL, Vec2::zero());
+        assert_eq!(a.velocity, Vec2::new(1.0, 0.0));
+        assert_eq!(b.velocity, Vec2::new(1.0, 0.0));
+    }
+
+    #[test]
+    fn the_bouncier_body_sets_restitution() {
+        let mut wall = BodyState::immovable(Vec2::new(1.0, 0.0), Vec2::zero());
+        let mut ball = body(-1.0, 4.0, 2.0, 0.5);
+        apply_contact_impulse(&mut ball, &mut wall, -NORMAL, Vec2::zero());
+        assert_eq!(ball.velocity, Vec2::new(-2.0, 0.0));
+        assert_eq!(wall.velocity, Vec2::zero());
+    }
+
+    #[test]
+    fn separating_or_immovable_pairs_are_left_alone() {
+        let (mut a, mut b) = (body(1.0, 2.0, 1.0, 1.0), body(-1.0, 1.0, 1.0, 1.0));
+        assert_eq!(
+            apply_contact_impulse(&mut a, &mut b, NORMAL, Vec2::zero()),
+            0.0
+        );
+        assert_eq!(a.velocity, Vec2::new(2.0, 0.0));
+
+        let mut a = BodyState::immovable(Vec2::zero(), Vec2::new(-1.0, 0.0));
+        let mut b = BodyState::immovable(Vec2::zero(), Vec2::new(1.0, 0.0));
+        assert_eq!(
+            apply_contact_impulse(&mut a, &mut b, NORMAL, Vec2::zero()),
+            0.0
+        );
+    }
+
+    #[test]
+    fn off_centre_hits_spin_the_body() {
+        let mut a = body(0.0, 0.0, 1.0, 0.0);
+        a.inv_inertia = 1.0;
+        let mut wall = BodyState::immovable(Vec2::new(0.0, -2.0), Vec2::new(0.0, 1.0));
+        // Hit from below, to the right of the centre: turns counter-clockwise.
+        apply_contact_impulse(&mut a, &mut wall, Vec2::new(0.0, 1.0), Vec2::new(1.0, -1.0));
+        assert!(a.velocity.y > 0.0);
+        assert!(a.angular_velocity > 0.0);
+    }
+
+    #[test]
+    fn correction_is_split_by_inverse_mass_past_the_slop() {
+        let (a, b) = (body(0.0, 0.0, 1.0, 0.0), body(0.0, 0.0, 3.0, 0.0));
+        let (push_a, push_b) = positional_correction(&a, &b, NORMAL, 2.5);
+        // 2.0 past the slop, 80% of it removed, three quarters of that by the light body.
+        assert!((push_a.x - 1.2).abs() < 1e-5);
+        assert!((push_b.x + 0.4).abs() < 1e-5);
+        assert_eq!((push_a.y, push_b.y), (0.0, 0.0));
+    }
+
+    #[test]
+    fn overlaps_within_the_slop_are_not_corrected() {
+        let (a, b) = (body(0.0, 0.0, 1.0, 0.0), body(0.0, 0.0, 1.0, 0.0));
+        let pushes = positional_correction(&a, &b, NORMAL, CORRECTION_SLOP);
+        assert_eq!(pushes, (Vec2::zero(), Vec2::zero()));
+
+        let wall = BodyState::immovable(Vec2::zero(), Vec2::zero());
+        let (push_a, push_b) = positional_correction(&a, &wall, NORMAL, 1.5);
+        assert!((push_a.x - 0.8).abs() < 1e-5);
+        assert_eq!(push_b, Vec2::zero());
+    }
+}
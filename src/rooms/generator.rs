@@ -0,0 +1,327 @@
+use super::door_position;
+use crate::components::*;
+use oorandom::Rand32;
+use specs::{Builder, Entity, World, WorldExt};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+
+const SIDES: [DoorType; 4] = [
+    DoorType::Right,
+    DoorType::Left,
+    DoorType::Top,
+    DoorType::Bottom,
+];
+
+fn step(cell: (i32, i32), side: DoorType) -> (i32, i32) {
+    match side {
+        DoorType::Right => (cell.0 + 1, cell.1),
+        DoorType::Left => (cell.0 - 1, cell.1),
+        DoorType::Top => (cell.0, cell.1 + 1),
+        DoorType::Bottom => (cell.0, cell.1 - 1),
+        DoorType::Middle => cell,
+    }
+}
+
+// Rooms sit on a grid of `cell_size` cells, each one centred in its cell, so a room
+// can be as large as a cell minus the gap kept between neighbours.
+pub struct DungeonGenerator {
+    pub seed: u64,
+    pub room_count: usize,
+    pub cell_size: Size,
+    pub min_room: Size,
+    pub max_room: Size,
+    // Chance of also joining a new room to neighbours other than the one it grew from.
+    pub loop_chance: f32,
+    // Also stack rooms on top of each other, joined by top and bottom doors. Off by
+    // default, since a player who can only walk sideways needs every door on a wall.
+    pub vertical_links: bool,
+}
+
+pub struct GeneratedRoom {
+    pub entity: Entity,
+    pub cell: (i32, i32),
+    pub pos: Position,
+    // Number of doors between this room and the start room.
+    pub depth: usize,
+}
+
+pub struct Dungeon {
+    pub rooms: Vec<GeneratedRoom>,
+    pub start: Entity,
+    pub boss: Entity,
+}
+
+impl DungeonGenerator {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            room_count: 8,
+            cell_size: Size::new(4000.0, 2000.0),
+            min_room: Size::new(1400.0, 770.0),
+            max_room: Size::new(3000.0, 770.0),
+            loop_chance: 0.2,
+            vertical_links: false,
+        }
+    }
+
+    // Builds the room entities with `Size`, `Doors` and the start and boss `SpecialRoom`s.
+    // The room graph only depends on the seed and the settings, never on the world.
+    pub fn generate(&self, world: &mut World) -> Dungeon {
+        let mut rng = Rand32::new(self.seed);
+        let sides: &[DoorType] = if self.vertical_links {
+            &SIDES
+        } else {
+            &SIDES[..2]
+        };
+
+        // Grow outwards from the start cell, one room next to an existing one at a time.
+        let mut cells = vec![(0, 0)];
+        let mut taken = BTreeMap::new();
+        taken.insert((0, 0), 0);
+        let mut links: Vec<(usize, DoorType, usize)> = Vec::new();
+        let wanted = self.room_count.max(2);
+        let mut attempts = 0;
+        while cells.len() < wanted && attempts < wanted * 100 {
+            attempts += 1;
+            let from = rng.rand_range(0..cells.len() as u32) as usize;
+            let side = sides[rng.rand_range(0..sides.len() as u32) as usize];
+            let cell = step(cells[from], side);
+            if taken.contains_key(&cell) {
+                continue;
+            }
+            let new = cells.len();
+            cells.push(cell);
+            taken.insert(cell, new);
+            links.push((from, side, new));
+
+            for &other_side in sides.iter() {
+                let neighbour = match taken.get(&step(cell, other_side)) {
+                    Some(&n) if n != from => n,
+                    _ => continue,
+                };
+                if rng.rand_float() < self.loop_chance {
+                    links.push((new, other_side, neighbour));
+                }
+            }
+        }
+
+        let sizes: Vec<Size> = cells
+            .iter()
+            .map(|_| {
+                let w = lerp(self.min_room.width, self.max_room.width, rng.rand_float());
+                let h = lerp(self.min_room.height, self.max_room.height, rng.rand_float());
+                Size::new(w, h)
+            })
+            .collect();
+        let positions: Vec<Position> = cells
+            .iter()
+            .map(|&(cx, cy)| {
+                Position::new(
+                    cx as f32 * self.cell_size.width,
+                    cy as f32 * self.cell_size.height,
+                )
+            })
+            .collect();
+
+        let mut doors: Vec<HashMap<DoorType, (usize, Position)>> =
+            vec![HashMap::new(); cells.len()];
+        for &(a, side, b) in links.iter() {
+            let pos_a = door_position(side, positions[a], &sizes[a]);
+            let pos_b = door_position(side.opposite(), positions[b], &sizes[b]);
+            doors[a].insert(side, (b, pos_a));
+            doors[b].insert(side.opposite(), (a, pos_b));
+        }
+
+        let depths = depths_from(0, &doors);
+        // Furthest room from the start, preferring the older room on ties.
+        let boss = (0..cells.len())
+            .max_by(|&a, &b| depths[a].cmp(&depths[b]).then(b.cmp(&a)))
+            .unwrap_or(0);
+
+        let entities: Vec<Entity> = sizes
+            .into_iter()
+            .map(|size| world.create_entity().with(size).build())
+            .collect();
+        {
+            let mut door_storage = world.write_storage::<Doors>();
+            let mut specials = world.write_storage::<SpecialRoom>();
+            for (i, room_doors) in doors.iter().enumerate() {
+                let map = room_doors
+                    .iter()
+                    .map(|(&side, &(to, pos))| {
+                        (
+                            side,
+                            Door {
+                                to_room: entities[to],
+                                pos,
+                            },
+                        )
+                    })
+                    .collect();
+                door_storage
+                    .insert(entities[i], Doors(map))
+                    .expect("Adding generated doors");
+            }
+            specials
+                .insert(entities[0], SpecialRoom::new(RoomType::Start))
+                .expect("Marking the start room");
+            specials
+                .insert(entities[boss], SpecialRoom::new(RoomType::Boss))
+                .expect("Marking the boss room");
+        }
+
+        let rooms = entities
+            .iter()
+            .enumerate()
+            .map(|(i, &entity)| GeneratedRoom {
+                entity,
+                cell: cells[i],
+                pos: positions[i],
+                depth: depths[i],
+            })
+            .collect();
+        Dungeon {
+            rooms,
+            start: entities[0],
+            boss: entities[boss],
+        }
+    }
+}
+
+fn lerp(a: f32, b: f32, t: f32) -> f32 {
+    a + (b - a) * t
+}
+
+fn depths_from(start: usize, doors: &[HashMap<DoorType, (usize, Position)>]) -> Vec<usize> {
+    let mut depths = vec![usize::MAX; doors.len()];
+    depths[start] = 0;
+    let mut queue = VecDeque::new();
+    queue.push_back(start);
+    while let Some(room) = queue.pop_front() {
+        for &(next, _) in doors[room].values() {
+            if depths[next] == usize::MAX {
+                depths[next] = depths[room] + 1;
+                queue.push_back(next);
+            }
+        }
+    }
+    depths
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::new_world;
+    use specs::Join;
+
+    type Cell = (i32, i32);
+
+    // Every room's cell with the sides and cells its doors lead to.
+    fn layout(seed: u64) -> Vec<(Cell, Vec<(DoorType, Cell)>)> {
+        let mut world = new_world();
+        let dungeon = DungeonGenerator::new(seed).generate(&mut world);
+        let cells: HashMap<Entity, (i32, i32)> =
+            dungeon.rooms.iter().map(|r| (r.entity, r.cell)).collect();
+        let doors = world.read_storage::<Doors>();
+        dungeon
+            .rooms
+            .iter()
+            .map(|r| {
+                let mut links: Vec<_> = doors
+                    .get(r.entity)
+                    .unwrap()
+                    .0
+                    .iter()
+                    .map(|(side, door)| (*side, cells[&door.to_room]))
+                    .collect();
+                links.sort_by_key(|(side, _)| *side as u8);
+                (r.cell, links)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn same_seed_gives_same_layout() {
+        for seed in 0..20 {
+            assert_eq!(layout(seed), layout(seed));
+        }
+    }
+
+    #[test]
+    fn different_seeds_give_different_layouts() {
+        let first = layout(1);
+        assert!((2..20).any(|seed| layout(seed) != first));
+    }
+
+    #[test]
+    fn generates_requested_room_count() {
+        let mut world = new_world();
+        let dungeon = DungeonGenerator::new(7).generate(&mut world);
+        assert_eq!(dungeon.rooms.len(), 8);
+        assert_eq!((&world.read_storage::<Size>()).join().count(), 8);
+    }
+
+    #[test]
+    fn doors_link_both_ways_through_opposite_sides() {
+        for (seed, vertical_links) in (0..20).zip([false, true].iter().cycle()) {
+            let mut world = new_world();
+            let mut generator = DungeonGenerator::new(seed);
+            generator.vertical_links = *vertical_links;
+            let dungeon = generator.generate(&mut world);
+            let doors = world.read_storage::<Doors>();
+            for room in dungeon.rooms.iter() {
+                for (side, door) in doors.get(room.entity).unwrap().0.iter() {
+                    let back = doors
+                        .get(door.to_room)
+                        .unwrap()
+                        .0
+                        .get(&side.opposite())
+                        .expect("Matching door on the other side");
+                    assert_eq!(back.to_room, room.entity);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn one_start_and_one_boss_at_the_far_end() {
+        for seed in 0..20 {
+            let mut world = new_world();
+            let dungeon = DungeonGenerator::new(seed).generate(&mut world);
+            let specials = world.read_storage::<SpecialRoom>();
+            let labels: Vec<RoomType> = (&specials).join().map(|s| *s.label()).collect();
+            assert_eq!(labels.iter().filter(|&&l| l == RoomType::Start).count(), 1);
+            assert_eq!(labels.iter().filter(|&&l| l == RoomType::Boss).count(), 1);
+
+            let boss = dungeon
+                .rooms
+                .iter()
+                .find(|r| r.entity == dungeon.boss)
+                .unwrap();
+            let deepest = dungeon.rooms.iter().map(|r| r.depth).max().unwrap();
+            assert_ne!(dungeon.boss, dungeon.start);
+            assert_eq!(boss.depth, deepest);
+            assert!(dungeon.rooms.iter().all(|r| r.depth != usize::MAX));
+        }
+    }
+
+    #[test]
+    fn dungeons_are_horizontal_by_default() {
+        for seed in 0..20 {
+            let mut world = new_world();
+            let dungeon = DungeonGenerator::new(seed).generate(&mut world);
+            assert!(dungeon.rooms.iter().all(|r| r.cell.1 == 0));
+        }
+    }
+
+    #[test]
+    fn vertical_links_stack_rooms() {
+        let stacked = (0..20).any(|seed| {
+            let mut world = new_world();
+            let mut generator = DungeonGenerator::new(seed);
+            generator.vertical_links = true;
+            let dungeon = generator.generate(&mut world);
+            dungeon.rooms.iter().any(|r| r.cell.1 != 0)
+        });
+        assert!(stacked);
+    }
+}
@@ -0,0 +1,208 @@
+use crate::components::*;
+use specs::Entity;
+
+mod generator;
+pub use self::generator::*;
+mod validation;
+pub use self::validation::*;
+
+pub const DOOR_WIDTH: f32 = 80.0;
+pub const DOOR_HEIGHT: f32 = 200.0;
+// Gap left between a door and whoever comes out of it, so they don't walk straight back in.
+const ARRIVAL_MARGIN: f32 = 10.0;
+
+#[derive(Default)]
+pub struct ActiveRoom(pub Option<Entity>);
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TransitionKind {
+    Fade,
+    Slide,
+}
+
+pub struct Transition {
+    pub kind: TransitionKind,
+    pub traveller: Entity,
+    pub to_room: Entity,
+    pub arrival: Position,
+    pub elapsed: f32,
+    pub duration: f32,
+    // Where the camera was looking when the transition started, in camera space.
+    pub cam_from: Option<Position>,
+    // Where it ends up, kept inside the room being entered.
+    pub cam_to: Option<Position>,
+    pub arrived: bool,
+}
+impl Transition {
+    pub fn new(
+        kind: TransitionKind,
+        traveller: Entity,
+        to_room: Entity,
+        arrival: Position,
+        duration: f32,
+    ) -> Self {
+        Self {
+            kind,
+            traveller,
+            to_room,
+            arrival,
+            elapsed: 0.0,
+            duration,
+            cam_from: None,
+            cam_to: None,
+            arrived: false,
+        }
+    }
+
+    pub fn progress(&self) -> f32 {
+        if self.duration <= 0.0 {
+            1.0
+        } else {
+            (self.elapsed / self.duration).min(1.0)
+        }
+    }
+
+    // A fade swaps rooms once the screen is black, a slide swaps straight away.
+    pub fn switch_point(&self) -> f32 {
+        match self.kind {
+            TransitionKind::Fade => 0.5,
+            TransitionKind::Slide => 0.0,
+        }
+    }
+
+    // Opacity of the black overlay drawn over the world.
+    pub fn fade_alpha(&self) -> f32 {
+        match self.kind {
+            TransitionKind::Fade => 1.0 - (2.0 * self.progress() - 1.0).abs(),
+            TransitionKind::Slide => 0.0,
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.arrived && self.progress() >= 1.0
+    }
+}
+
+#[derive(Default)]
+pub struct RoomTransition(pub Option<Transition>);
+
+// Centre of a door of the given type in a room, with side doors standing on the floor.
+pub fn door_position(door_type: DoorType, room_pos: Position, room: &Size) -> Position {
+    let (half_w, half_h) = (room.width / 2.0, room.height / 2.0);
+    let floor_y = room_pos.y - half_h + DOOR_HEIGHT / 2.0;
+    match door_type {
+        DoorType::Right => Position::new(room_pos.x + half_w - DOOR_WIDTH / 2.0, floor_y),
+        DoorType::Left => Position::new(room_pos.x - half_w + DOOR_WIDTH / 2.0, floor_y),
+        DoorType::Middle => Position::new(room_pos.x, floor_y),
+        DoorType::Top => Position::new(room_pos.x, room_pos.y + half_h - DOOR_HEIGHT / 2.0),
+        DoorType::Bottom => Position::new(room_pos.x, room_pos.y - half_h + DOOR_HEIGHT / 2.0),
+    }
+}
+
+// Where a traveller of the given size should appear when coming out of a door, just
+// clear of it on the room side. Bottom doors are trapdoors, so whoever climbs out of
+// one lands on the floor beside it rather than on top of it.
+pub fn arrival_point(door_type: DoorType, door_pos: Position, traveller: &Size) -> Position {
+    let clear_x = DOOR_WIDTH / 2.0 + traveller.width / 2.0 + ARRIVAL_MARGIN;
+    let clear_y = DOOR_HEIGHT / 2.0 + traveller.height / 2.0 + ARRIVAL_MARGIN;
+    let feet_y = door_pos.y - DOOR_HEIGHT / 2.0 + traveller.height / 2.0;
+    match door_type {
+        DoorType::Right => Position::new(door_pos.x - clear_x, feet_y),
+        DoorType::Left | DoorType::Middle | DoorType::Bottom => {
+            Position::new(door_pos.x + clear_x, feet_y)
+        }
+        DoorType::Top => Position::new(door_pos.x, door_pos.y - clear_y),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::physics::Aabb;
+
+    const SIDES: [DoorType; 5] = [
+        DoorType::Right,
+        DoorType::Left,
+        DoorType::Middle,
+        DoorType::Top,
+        DoorType::Bottom,
+    ];
+
+    fn room() -> (Position, Size) {
+        (Position::new(300.0, -200.0), Size::new(1200.0, 800.0))
+    }
+
+    fn traveller() -> Size {
+        Size::new(40.0, 60.0)
+    }
+
+    fn door_box(pos: Position) -> Aabb {
+        Aabb::from_size(pos.into(), DOOR_WIDTH, DOOR_HEIGHT)
+    }
+
+    fn traveller_box(pos: Position) -> Aabb {
+        let size = traveller();
+        Aabb::from_size(pos.into(), size.width, size.height)
+    }
+
+    fn contains(outer: &Aabb, inner: &Aabb) -> bool {
+        let (o_min, o_max, i_min, i_max) = (outer.min(), outer.max(), inner.min(), inner.max());
+        i_min.x >= o_min.x && i_min.y >= o_min.y && i_max.x <= o_max.x && i_max.y <= o_max.y
+    }
+
+    #[test]
+    fn doors_sit_against_their_walls() {
+        let (pos, size) = room();
+        let bounds = Aabb::from_size(pos.into(), size.width, size.height);
+        let (min, max) = (bounds.min(), bounds.max());
+        for &side in SIDES.iter() {
+            let door = door_box(door_position(side, pos, &size));
+            assert!(contains(&bounds, &door), "{:?} door outside the room", side);
+            match side {
+                DoorType::Right => assert_eq!(door.max().x, max.x),
+                DoorType::Left => assert_eq!(door.min().x, min.x),
+                DoorType::Top => assert_eq!(door.max().y, max.y),
+                DoorType::Middle | DoorType::Bottom => assert_eq!(door.center.x, pos.x),
+            }
+            if side != DoorType::Top {
+                assert_eq!(door.min().y, min.y, "{:?} door off the floor", side);
+            }
+        }
+    }
+
+    #[test]
+    fn arrivals_are_clear_of_the_door_and_inside_the_room() {
+        let (pos, size) = room();
+        let bounds = Aabb::from_size(pos.into(), size.width, size.height);
+        for &side in SIDES.iter() {
+            let door_pos = door_position(side, pos, &size);
+            let arrival = traveller_box(arrival_point(side, door_pos, &traveller()));
+            assert!(
+                !arrival.overlaps(&door_box(door_pos)),
+                "{:?} arrival in its door",
+                side
+            );
+            assert!(
+                contains(&bounds, &arrival),
+                "{:?} arrival outside the room",
+                side
+            );
+        }
+    }
+
+    #[test]
+    fn arrivals_stand_on_the_floor_except_under_top_doors() {
+        let (pos, size) = room();
+        let floor = pos.y - size.height / 2.0;
+        for &side in SIDES.iter() {
+            let door_pos = door_position(side, pos, &size);
+            let arrival = traveller_box(arrival_point(side, door_pos, &traveller()));
+            if side == DoorType::Top {
+                // Drops out of the ceiling and falls away from the door.
+                assert!(arrival.max().y < door_box(door_pos).min().y);
+            } else {
+                assert_eq!(arrival.min().y, floor, "{:?} arrival in the air", side);
+            }
+        }
+    }
+}
@@ -0,0 +1,401 @@
+use crate::components::*;
+use specs::{Entity, Join, World, WorldExt};
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum RoomGraphError {
+    // The door leads to an entity that is dead or has no `Doors`.
+    DanglingDoor {
+        room: Entity,
+        side: DoorType,
+        to_room: Entity,
+    },
+    // Nothing in `to_room` leads back to `room`.
+    OneWayLink {
+        room: Entity,
+        side: DoorType,
+        to_room: Entity,
+    },
+    // The way back exists but is not on the side opposite the door.
+    MismatchedSide {
+        room: Entity,
+        side: DoorType,
+        to_room: Entity,
+        back_side: DoorType,
+    },
+    Unreachable {
+        room: Entity,
+        start: Entity,
+    },
+    MissingSpecialRoom(RoomType),
+    DuplicateSpecialRoom(RoomType, usize),
+    BossUnreachable {
+        start: Entity,
+        boss: Entity,
+    },
+}
+impl fmt::Display for RoomGraphError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use RoomGraphError::*;
+        match self {
+            DanglingDoor {
+                room,
+                side,
+                to_room,
+            } => write!(
+                f,
+                "{:?} door of room {} leads to entity {}, which is not a room",
+                side,
+                room.id(),
+                to_room.id()
+            ),
+            OneWayLink {
+                room,
+                side,
+                to_room,
+            } => write!(
+                f,
+                "{:?} door of room {} leads to room {}, which has no door back",
+                side,
+                room.id(),
+                to_room.id()
+            ),
+            MismatchedSide {
+                room,
+                side,
+                to_room,
+                back_side,
+            } => write!(
+                f,
+                "{:?} door of room {} comes back from room {} through its {:?} side instead of {:?}",
+                side,
+                room.id(),
+                to_room.id(),
+                back_side,
+                side.opposite()
+            ),
+            Unreachable { room, start } => write!(
+                f,
+                "room {} cannot be reached from start room {}",
+                room.id(),
+                start.id()
+            ),
+            MissingSpecialRoom(label) => write!(f, "there is no {:?} room", label),
+            DuplicateSpecialRoom(label, count) => {
+                write!(f, "there are {} {:?} rooms, expected one", count, label)
+            }
+            BossUnreachable { start, boss } => write!(
+                f,
+                "boss room {} cannot be reached from start room {}",
+                boss.id(),
+                start.id()
+            ),
+        }
+    }
+}
+
+// A snapshot of every room in the world and where its doors lead. Any living entity
+// with `Doors` counts as a room, so a door into anything else is dangling.
+pub struct RoomGraph {
+    rooms: BTreeMap<Entity, Vec<(DoorType, Entity)>>,
+    start: Vec<Entity>,
+    boss: Vec<Entity>,
+}
+impl RoomGraph {
+    pub fn from_world(world: &World) -> Self {
+        let entities = world.entities();
+        let doors = world.read_storage::<Doors>();
+        let specials = world.read_storage::<SpecialRoom>();
+
+        let mut rooms = BTreeMap::new();
+        for (room, Doors(room_doors)) in (&entities, &doors).join() {
+            let mut links: Vec<(DoorType, Entity)> = room_doors
+                .iter()
+                .map(|(side, door)| (*side, door.to_room))
+                .collect();
+            links.sort_by_key(|(side, _)| *side as u8);
+            rooms.insert(room, links);
+        }
+
+        let (mut start, mut boss) = (Vec::new(), Vec::new());
+        for (room, special, _) in (&entities, &specials, &doors).join() {
+            match special.label() {
+                RoomType::Start => start.push(room),
+                RoomType::Boss => boss.push(room),
+            }
+        }
+
+        Self { rooms, start, boss }
+    }
+
+    pub fn rooms(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.rooms.keys().cloned()
+    }
+
+    pub fn start(&self) -> Option<Entity> {
+        self.start.first().cloned()
+    }
+
+    pub fn boss(&self) -> Option<Entity> {
+        self.boss.first().cloned()
+    }
+
+    // Number of doors needed to get from `from` to every room it can reach.
+    pub fn distances_from(&self, from: Entity) -> BTreeMap<Entity, usize> {
+        let mut distances = BTreeMap::new();
+        if !self.rooms.contains_key(&from) {
+            return distances;
+        }
+        distances.insert(from, 0);
+        let mut queue = VecDeque::new();
+        queue.push_back(from);
+        while let Some(room) = queue.pop_front() {
+            let next_distance = distances[&room] + 1;
+            for (_, to_room) in self.rooms[&room].iter() {
+                if self.rooms.contains_key(to_room) && !distances.contains_key(to_room) {
+                    distances.insert(*to_room, next_distance);
+                    queue.push_back(*to_room);
+                }
+            }
+        }
+        distances
+    }
+
+    pub fn validate(&self) -> Result<(), Vec<RoomGraphError>> {
+        use RoomGraphError::*;
+        let mut errors = Vec::new();
+
+        for (&room, links) in self.rooms.iter() {
+            for &(side, to_room) in links.iter() {
+                let back_links = match self.rooms.get(&to_room) {
+                    Some(back_links) => back_links,
+                    _ => {
+                        errors.push(DanglingDoor {
+                            room,
+                            side,
+                            to_room,
+                        });
+                        continue;
+                    }
+                };
+                let back_sides: Vec<DoorType> = back_links
+                    .iter()
+                    .filter(|(_, back_room)| *back_room == room)
+                    .map(|(back_side, _)| *back_side)
+                    .collect();
+                if back_sides.is_empty() {
+                    errors.push(OneWayLink {
+                        room,
+                        side,
+                        to_room,
+                    });
+                } else if !back_sides.contains(&side.opposite()) {
+                    errors.push(MismatchedSide {
+                        room,
+                        side,
+                        to_room,
+                        back_side: back_sides[0],
+                    });
+                }
+            }
+        }
+
+        for (label, found) in [(RoomType::Start, &self.start), (RoomType::Boss, &self.boss)].iter()
+        {
+            match found.len() {
+                0 => errors.push(MissingSpecialRoom(*label)),
+                1 => (),
+                count => errors.push(DuplicateSpecialRoom(*label, count)),
+            }
+        }
+
+        if let Some(start) = self.start() {
+            let reachable = self.distances_from(start);
+            for room in self.rooms() {
+                if reachable.contains_key(&room) {
+                    continue;
+                }
+                if self.boss() == Some(room) {
+                    errors.push(BossUnreachable { start, boss: room });
+                } else {
+                    errors.push(Unreachable { room, start });
+                }
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+pub fn validate_rooms(world: &World) -> Result<(), Vec<RoomGraphError>> {
+    RoomGraph::from_world(world).validate()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::new_world;
+    use crate::rooms::DungeonGenerator;
+    use specs::Builder;
+    use std::collections::HashMap;
+
+    fn add_room(world: &mut World, label: Option<RoomType>) -> Entity {
+        let builder = world.create_entity().with(Doors(HashMap::new()));
+        match label {
+            Some(label) => builder.with(SpecialRoom::new(label)).build(),
+            None => builder.build(),
+        }
+    }
+
+    fn add_door(world: &mut World, room: Entity, side: DoorType, to_room: Entity) {
+        let mut doors = world.write_storage::<Doors>();
+        let Doors(room_doors) = doors.get_mut(room).unwrap();
+        room_doors.insert(
+            side,
+            Door {
+                to_room,
+                pos: Position::new(0.0, 0.0),
+            },
+        );
+    }
+
+    fn link(world: &mut World, a: Entity, side: DoorType, b: Entity) {
+        add_door(world, a, side, b);
+        add_door(world, b, side.opposite(), a);
+    }
+
+    #[test]
+    fn generated_dungeons_are_valid() {
+        for (seed, vertical_links) in (0..20).zip([false, true].iter().cycle()) {
+            let mut world = new_world();
+            let mut generator = DungeonGenerator::new(seed);
+            generator.vertical_links = *vertical_links;
+            generator.generate(&mut world);
+            assert_eq!(validate_rooms(&world), Ok(()));
+        }
+    }
+
+    #[test]
+    fn linked_start_and_boss_are_valid() {
+        let mut world = new_world();
+        let start = add_room(&mut world, Some(RoomType::Start));
+        let boss = add_room(&mut world, Some(RoomType::Boss));
+        link(&mut world, start, DoorType::Right, boss);
+
+        let graph = RoomGraph::from_world(&world);
+        assert_eq!(graph.validate(), Ok(()));
+        assert_eq!(graph.distances_from(start).get(&boss), Some(&1));
+    }
+
+    #[test]
+    fn reports_dangling_doors() {
+        let mut world = new_world();
+        let start = add_room(&mut world, Some(RoomType::Start));
+        let boss = add_room(&mut world, Some(RoomType::Boss));
+        link(&mut world, start, DoorType::Right, boss);
+        let not_a_room = world.create_entity().with(Size::new(1.0, 1.0)).build();
+        let dead = world.create_entity().build();
+        world.delete_entity(dead).unwrap();
+        add_door(&mut world, boss, DoorType::Right, not_a_room);
+        add_door(&mut world, start, DoorType::Left, dead);
+
+        let errors = validate_rooms(&world).unwrap_err();
+        assert!(errors.contains(&RoomGraphError::DanglingDoor {
+            room: boss,
+            side: DoorType::Right,
+            to_room: not_a_room,
+        }));
+        assert!(errors.contains(&RoomGraphError::DanglingDoor {
+            room: start,
+            side: DoorType::Left,
+            to_room: dead,
+        }));
+    }
+
+    #[test]
+    fn reports_one_way_links_and_mismatched_sides() {
+        let mut world = new_world();
+        let start = add_room(&mut world, Some(RoomType::Start));
+        let boss = add_room(&mut world, Some(RoomType::Boss));
+        let side_room = add_room(&mut world, None);
+        add_door(&mut world, start, DoorType::Right, boss);
+        add_door(&mut world, start, DoorType::Top, side_room);
+        add_door(&mut world, side_room, DoorType::Left, start);
+
+        let errors = validate_rooms(&world).unwrap_err();
+        assert!(errors.contains(&RoomGraphError::OneWayLink {
+            room: start,
+            side: DoorType::Right,
+            to_room: boss,
+        }));
+        assert!(errors.contains(&RoomGraphError::MismatchedSide {
+            room: start,
+            side: DoorType::Top,
+            to_room: side_room,
+            back_side: DoorType::Left,
+        }));
+    }
+
+    #[test]
+    fn reports_unreachable_rooms_and_boss() {
+        let mut world = new_world();
+        let start = add_room(&mut world, Some(RoomType::Start));
+        let boss = add_room(&mut world, Some(RoomType::Boss));
+        let island = add_room(&mut world, None);
+        link(&mut world, boss, DoorType::Right, island);
+
+        let errors = validate_rooms(&world).unwrap_err();
+        assert_eq!(
+            errors,
+            vec![
+                RoomGraphError::BossUnreachable { start, boss },
+                RoomGraphError::Unreachable {
+                    room: island,
+                    start
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_missing_and_duplicate_special_rooms() {
+        let mut world = new_world();
+        let a = add_room(&mut world, Some(RoomType::Boss));
+        let b = add_room(&mut world, Some(RoomType::Boss));
+        link(&mut world, a, DoorType::Right, b);
+
+        let errors = validate_rooms(&world).unwrap_err();
+        assert_eq!(
+            errors,
+            vec![
+                RoomGraphError::MissingSpecialRoom(RoomType::Start),
+                RoomGraphError::DuplicateSpecialRoom(RoomType::Boss, 2),
+            ]
+        );
+    }
+
+    #[test]
+    fn errors_read_well() {
+        let mut world = new_world();
+        let start = add_room(&mut world, Some(RoomType::Start));
+        let boss = add_room(&mut world, Some(RoomType::Boss));
+        let error = RoomGraphError::OneWayLink {
+            room: start,
+            side: DoorType::Right,
+            to_room: boss,
+        };
+        assert_eq!(
+            error.to_string(),
+            format!(
+                "Right door of room {} leads to room {}, which has no door back",
+                start.id(),
+                boss.id()
+            )
+        );
+    }
+}
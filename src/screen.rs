@@ -0,0 +1,106 @@
+use ggez::graphics::Rect;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ScalingPolicy {
+    // Keeps the virtual resolution and fits it in the window, with bars on the sides
+    // that don't match the aspect ratio.
+    Letterbox,
+    // Shows more of the world as the window grows, one screen unit per pixel.
+    Expand,
+    // Like `Letterbox`, but only ever scales by whole numbers so pixels stay square.
+    PixelPerfect,
+}
+
+// How screen coordinates map onto a window of any size.
+pub struct ScreenScaling {
+    pub policy: ScalingPolicy,
+    // The resolution the game is laid out for.
+    pub virtual_width: f32,
+    pub virtual_height: f32,
+}
+impl ScreenScaling {
+    pub fn new(policy: ScalingPolicy, virtual_width: f32, virtual_height: f32) -> Self {
+        Self {
+            policy,
+            virtual_width,
+            virtual_height,
+        }
+    }
+
+    // The screen coordinates to use in a window of this size. With bars, the virtual
+    // screen still runs from (0, 0) to the virtual size and the bars sit outside it.
+    pub fn screen_rect(&self, win_w: f32, win_h: f32) -> Rect {
+        if win_w <= 0.0 || win_h <= 0.0 {
+            return Rect::new(0.0, 0.0, self.virtual_width, self.virtual_height);
+        }
+        let fit = (win_w / self.virtual_width).min(win_h / self.virtual_height);
+        let scale = match self.policy {
+            ScalingPolicy::Expand => return Rect::new(0.0, 0.0, win_w, win_h),
+            ScalingPolicy::Letterbox => fit,
+            ScalingPolicy::PixelPerfect => fit.floor().max(1.0),
+        };
+        let (w, h) = (win_w / scale, win_h / scale);
+        Rect::new(
+            -(w - self.virtual_width) / 2.0,
+            -(h - self.virtual_height) / 2.0,
+            w,
+            h,
+        )
+    }
+
+    // The screen size camera viewports should be laid out for.
+    pub fn layout_size(&self, win_w: f32, win_h: f32) -> (f32, f32) {
+        match self.policy {
+            ScalingPolicy::Expand if win_w > 0.0 && win_h > 0.0 => (win_w, win_h),
+            _ => (self.virtual_width, self.virtual_height),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scaling(policy: ScalingPolicy) -> ScreenScaling {
+        ScreenScaling::new(policy, 800.0, 600.0)
+    }
+
+    #[test]
+    fn letterbox_adds_bars_on_the_long_side() {
+        let wide = scaling(ScalingPolicy::Letterbox).screen_rect(1600.0, 600.0);
+        assert_eq!(wide, Rect::new(-400.0, 0.0, 1600.0, 600.0));
+        let tall = scaling(ScalingPolicy::Letterbox).screen_rect(400.0, 600.0);
+        assert_eq!(tall, Rect::new(0.0, -300.0, 800.0, 1200.0));
+    }
+
+    #[test]
+    fn expand_maps_pixels_one_to_one() {
+        let policy = scaling(ScalingPolicy::Expand);
+        assert_eq!(
+            policy.screen_rect(1024.0, 700.0),
+            Rect::new(0.0, 0.0, 1024.0, 700.0)
+        );
+        assert_eq!(policy.layout_size(1024.0, 700.0), (1024.0, 700.0));
+    }
+
+    #[test]
+    fn pixel_perfect_scales_by_whole_numbers() {
+        let policy = scaling(ScalingPolicy::PixelPerfect);
+        // 1.9x fits, so it drops to 1x and centres the virtual screen.
+        assert_eq!(
+            policy.screen_rect(1520.0, 1140.0),
+            Rect::new(-360.0, -270.0, 1520.0, 1140.0)
+        );
+        assert_eq!(
+            policy.screen_rect(1700.0, 1200.0),
+            Rect::new(-25.0, 0.0, 850.0, 600.0)
+        );
+        assert_eq!(policy.layout_size(1700.0, 1200.0), (800.0, 600.0));
+    }
+
+    #[test]
+    fn tiny_windows_never_scale_below_one() {
+        let rect = scaling(ScalingPolicy::PixelPerfect).screen_rect(400.0, 300.0);
+        assert_eq!(rect, Rect::new(200.0, 150.0, 400.0, 300.0));
+    }
+}
@@ -0,0 +1,74 @@
+use crate::{
+    components::*, game::DeltaTime, physics::Vec2, Animation, AnimationEvent, AnimationEvents,
+    AnimationInput, AnimationStateMachine,
+};
+use specs::{Entities, Join, Read, ReadStorage, System, Write, WriteStorage};
+
+// Lets each state machine pick the clip its animation plays.
+pub struct AnimationStateSystem;
+impl<'a> System<'a> for AnimationStateSystem {
+    type SystemData = (
+        WriteStorage<'a, AnimationStateMachine>,
+        WriteStorage<'a, Animation>,
+        ReadStorage<'a, IntentToMove>,
+        ReadStorage<'a, Velocity>,
+        ReadStorage<'a, PlatformerController>,
+        ReadStorage<'a, Facing>,
+    );
+
+    fn run(
+        &mut self,
+        (mut machines, mut anims, int_moves, vels, controllers, facings): Self::SystemData,
+    ) {
+        for (machine, anim, int_move, vel, ctrl, facing) in (
+            &mut machines,
+            &mut anims,
+            (&int_moves).maybe(),
+            (&vels).maybe(),
+            (&controllers).maybe(),
+            (&facings).maybe(),
+        )
+            .join()
+        {
+            let input = AnimationInput {
+                moving: int_move.is_some_and(|IntentToMove(m)| !m.is_empty()),
+                velocity: vel.map_or(Vec2::zero(), |v| v.cur),
+                // Entities that don't jump are always on the ground.
+                grounded: ctrl.map_or(true, |c| c.grounded),
+                facing: facing.map(|f| f.direction),
+            };
+            machine.step(anim, &input);
+        }
+    }
+}
+
+// Advances animations and points their sprites at the current frame.
+pub struct AnimationSystem;
+impl<'a> System<'a> for AnimationSystem {
+    type SystemData = (
+        Entities<'a>,
+        Read<'a, DeltaTime>,
+        Write<'a, AnimationEvents>,
+        WriteStorage<'a, Animation>,
+        WriteStorage<'a, Sprite>,
+    );
+
+    fn run(&mut self, (entities, dt, mut events, mut anims, mut sprites): Self::SystemData) {
+        let DeltaTime(dt) = *dt;
+        let AnimationEvents(events) = &mut *events;
+        events.clear();
+
+        for (e, anim, sprite) in (&entities, &mut anims, &mut sprites).join() {
+            if let Some(kind) = anim.update(dt) {
+                events.push(AnimationEvent {
+                    entity: e,
+                    clip: anim.current().to_string(),
+                    kind,
+                });
+            }
+            if let Some(frame) = anim.frame_rect() {
+                sprite.draw_param = Some(sprite.draw_param.unwrap_or_default().src(frame));
+            }
+        }
+    }
+}
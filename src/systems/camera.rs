@@ -0,0 +1,278 @@
+use super::{interpolate, room_bounds};
+use crate::{
+    components::*,
+    game::DeltaTime,
+    physics::Vec2,
+    rooms::{ActiveRoom, RoomTransition},
+    Camera, CameraEffects, CameraEvents,
+};
+use specs::{Join, Read, ReadStorage, System, Write, WriteStorage};
+
+// Moves the smallest distance that brings `diff` back inside `[-deadzone, deadzone]`.
+fn outside_deadzone(diff: f32, deadzone: f32) -> f32 {
+    if diff > deadzone {
+        diff - deadzone
+    } else if diff < -deadzone {
+        diff + deadzone
+    } else {
+        0.0
+    }
+}
+
+// Keeps cameras with a `Target` on that target. Cameras being moved by hand and cameras
+// in the middle of a room transition are left alone.
+//
+// Runs once per frame rather than per tick, chasing the target where the renderer draws
+// it, `alpha` of the way between ticks. `dt` is the time since the last frame.
+pub struct CameraFollowSystem {
+    alpha: f64,
+    dt: f32,
+}
+impl CameraFollowSystem {
+    pub fn new(alpha: f64, dt: f32) -> Self {
+        Self { alpha, dt }
+    }
+}
+impl<'a> System<'a> for CameraFollowSystem {
+    type SystemData = (
+        Read<'a, RoomTransition>,
+        WriteStorage<'a, Camera>,
+        WriteStorage<'a, Target>,
+        ReadStorage<'a, IntentToMove>,
+        ReadStorage<'a, Transform>,
+        ReadStorage<'a, Facing>,
+    );
+
+    fn run(
+        &mut self,
+        (transition, mut cams, mut targets, int_moves, transforms, facings): Self::SystemData,
+    ) {
+        if transition.0.is_some() {
+            return;
+        }
+        let dt = self.dt;
+
+        for (cam, target, _) in (&mut cams, &mut targets, !&int_moves).join() {
+            let transform = match transforms.get(target.entity) {
+                Some(transform) => transform,
+                None => continue,
+            };
+            let t = if target.smoothing <= 0.0 {
+                1.0
+            } else {
+                1.0 - (-target.smoothing * dt).exp()
+            };
+            let facing = facings.get(target.entity).map(|f| f.direction);
+            let look_ahead = target.update_look_ahead(facing, t);
+
+            // Work in world space, where Y points up.
+            let drawn_at = interpolate(transform, self.alpha);
+            let focus = Vec2::from(drawn_at) + Vec2::new(look_ahead, target.offset);
+            let cam_pos = Vec2::new(cam.cur_pos.x, -cam.cur_pos.y);
+            let diff = focus - cam_pos;
+            let step = Vec2::new(
+                outside_deadzone(diff.x, target.deadzone.x),
+                outside_deadzone(diff.y, target.deadzone.y),
+            );
+            let new_pos = cam_pos + step * t;
+
+            // Already where it should be drawn this frame, so there is nothing to
+            // interpolate. The bounds are applied here for the same reason.
+            cam.prev_pos = None;
+            cam.cur_pos = cam.clamp(Position::new(new_pos.x, -new_pos.y));
+        }
+    }
+}
+
+// Points the bounds of `ConfineToRoom` cameras at the active room, then clamps every
+// bounded camera. Room transitions move the camera themselves, so nothing is clamped
+// while one is running.
+pub struct CameraBoundsSystem;
+impl<'a> System<'a> for CameraBoundsSystem {
+    type SystemData = (
+        Read<'a, ActiveRoom>,
+        Read<'a, RoomTransition>,
+        WriteStorage<'a, Camera>,
+        ReadStorage<'a, ConfineToRoom>,
+        ReadStorage<'a, Transform>,
+        ReadStorage<'a, Size>,
+    );
+
+    fn run(
+        &mut self,
+        (active_room, transition, mut cams, confined, transforms, sizes): Self::SystemData,
+    ) {
+        let room_bounds = active_room
+            .0
+            .and_then(|room| Some(room_bounds(transforms.get(room)?, sizes.get(room)?)));
+
+        for (cam, _) in (&mut cams, &confined).join() {
+            if let Some(bounds) = room_bounds {
+                cam.bounds = Some(bounds);
+            }
+        }
+
+        if transition.0.is_some() {
+            return;
+        }
+        for cam in (&mut cams).join() {
+            cam.cur_pos = cam.clamp(cam.cur_pos);
+        }
+    }
+}
+
+pub struct CameraZoomSystem;
+impl<'a> System<'a> for CameraZoomSystem {
+    type SystemData = (Read<'a, DeltaTime>, WriteStorage<'a, Camera>);
+
+    fn run(&mut self, (dt, mut cams): Self::SystemData) {
+        let DeltaTime(dt) = *dt;
+        for cam in (&mut cams).join() {
+            let t = 1.0 - (-cam.zoom_smoothing * dt).exp();
+            cam.update_zoom(t);
+        }
+    }
+}
+
+// Hands this tick's camera events to every camera with effects and refreshes what they
+// add on top of the camera.
+pub struct CameraEffectsSystem;
+impl<'a> System<'a> for CameraEffectsSystem {
+    type SystemData = (
+        Read<'a, DeltaTime>,
+        Write<'a, CameraEvents>,
+        WriteStorage<'a, Camera>,
+        WriteStorage<'a, CameraEffects>,
+    );
+
+    fn run(&mut self, (dt, mut events, mut cams, mut effects): Self::SystemData) {
+        let DeltaTime(dt) = *dt;
+        let CameraEvents(events) = &mut *events;
+        for (cam, effects) in (&mut cams, &mut effects).join() {
+            for event in events.iter() {
+                effects.apply(event);
+            }
+            if effects.is_idle() && cam.prev_effect_offset.is_none() {
+                continue;
+            }
+            let (offset, zoom) = effects.update(dt, cam.cur_pos);
+            cam.prev_effect_offset = Some(cam.effect_offset);
+            cam.prev_effect_zoom = Some(cam.effect_zoom);
+            cam.effect_offset = offset;
+            cam.effect_zoom = zoom;
+            if effects.is_idle() && cam.prev_effect_offset == Some(offset) {
+                cam.prev_effect_offset = None;
+                cam.prev_effect_zoom = None;
+            }
+        }
+        events.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::new_world;
+    use ggez::graphics::Rect;
+    use specs::{Builder, Entity, RunNow, World, WorldExt};
+
+    // A camera at the origin following an entity that moved from `prev` to `cur`.
+    fn follow(
+        world: &mut World,
+        prev: Position,
+        cur: Position,
+        target: impl Fn(Entity) -> Target,
+    ) -> Entity {
+        let mut transform = Transform::new(cur);
+        transform.prev_pos = Some(prev);
+        let player = world.create_entity().with(transform).build();
+        world
+            .create_entity()
+            .with(Camera::default())
+            .with(target(player))
+            .build()
+    }
+
+    fn cam_pos(world: &World, cam: Entity) -> Position {
+        world.read_storage::<Camera>().get(cam).unwrap().cur_pos
+    }
+
+    #[test]
+    fn follows_the_position_the_target_is_drawn_at() {
+        let mut world = new_world();
+        let (prev, cur) = (Position::new(100.0, 40.0), Position::new(200.0, 80.0));
+        let cam = follow(&mut world, prev, cur, |e| Target::new(e, 10.0));
+        CameraFollowSystem::new(0.25, 0.01).run_now(&world);
+
+        // Camera space has Y pointing down.
+        assert_eq!(cam_pos(&world, cam), Position::new(125.0, -60.0));
+        assert_eq!(
+            world.read_storage::<Camera>().get(cam).unwrap().prev_pos,
+            None
+        );
+    }
+
+    #[test]
+    fn targets_inside_the_deadzone_leave_the_camera_alone() {
+        let mut world = new_world();
+        let at = Position::new(30.0, -20.0);
+        let cam = follow(&mut world, at, at, |e| {
+            let mut target = Target::new(e, 0.0);
+            target.deadzone = Vec2::new(50.0, 25.0);
+            target
+        });
+        CameraFollowSystem::new(1.0, 0.01).run_now(&world);
+        assert_eq!(cam_pos(&world, cam), Position::new(0.0, 0.0));
+    }
+
+    #[test]
+    fn leaving_the_deadzone_drags_the_camera_to_its_edge() {
+        let mut world = new_world();
+        let at = Position::new(80.0, -40.0);
+        let cam = follow(&mut world, at, at, |e| {
+            let mut target = Target::new(e, 0.0);
+            target.deadzone = Vec2::new(50.0, 25.0);
+            target
+        });
+        CameraFollowSystem::new(1.0, 0.01).run_now(&world);
+        assert_eq!(cam_pos(&world, cam), Position::new(30.0, 15.0));
+    }
+
+    #[test]
+    fn smoothing_closes_part_of_the_gap() {
+        let mut world = new_world();
+        let at = Position::new(100.0, 0.0);
+        let cam = follow(&mut world, at, at, |e| {
+            let mut target = Target::new(e, 0.0);
+            target.smoothing = 5.0;
+            target
+        });
+        CameraFollowSystem::new(1.0, 0.1).run_now(&world);
+        let x = cam_pos(&world, cam).x;
+        assert!((x - 100.0 * (1.0 - (-0.5f32).exp())).abs() < 1e-3);
+    }
+
+    #[test]
+    fn following_stays_inside_the_bounds() {
+        let mut world = new_world();
+        let at = Position::new(5000.0, 5000.0);
+        let cam = follow(&mut world, at, at, |e| Target::new(e, 0.0));
+        world.write_storage::<Camera>().get_mut(cam).unwrap().bounds =
+            Some(Rect::new(-1000.0, -500.0, 2000.0, 1000.0));
+        CameraFollowSystem::new(1.0, 0.01).run_now(&world);
+        assert_eq!(cam_pos(&world, cam), Position::new(600.0, -200.0));
+    }
+
+    #[test]
+    fn cameras_moved_by_hand_are_left_alone() {
+        let mut world = new_world();
+        let at = Position::new(100.0, 100.0);
+        let cam = follow(&mut world, at, at, |e| Target::new(e, 0.0));
+        world
+            .write_storage::<IntentToMove>()
+            .insert(cam, IntentToMove(Default::default()))
+            .unwrap();
+        CameraFollowSystem::new(1.0, 0.01).run_now(&world);
+        assert_eq!(cam_pos(&world, cam), Position::new(0.0, 0.0));
+    }
+}
@@ -0,0 +1,91 @@
+use crate::{
+    components::*,
+    physics::{Aabb, BodyType, CollisionLayer, CollisionWorld, Vec2},
+};
+use specs::{Entities, Join, Read, ReadStorage, System, Write, WriteStorage};
+
+pub struct ColliderSyncSystem;
+impl<'a> System<'a> for ColliderSyncSystem {
+    type SystemData = (
+        Entities<'a>,
+        Write<'a, CollisionWorld>,
+        ReadStorage<'a, Collider>,
+        ReadStorage<'a, CollisionLayer>,
+        ReadStorage<'a, Size>,
+        ReadStorage<'a, Transform>,
+        ReadStorage<'a, RigidBody>,
+    );
+
+    fn run(
+        &mut self,
+        (entities, mut world, colliders, layers, sizes, transforms, bodies): Self::SystemData,
+    ) {
+        for (e, col, layer, size, transform, body) in (
+            &entities,
+            &colliders,
+            (&layers).maybe(),
+            &sizes,
+            &transforms,
+            (&bodies).maybe(),
+        )
+            .join()
+        {
+            let mut aabb = Aabb::from_size(Vec2::from(transform.cur_pos), size.width, size.height);
+            if let Some(body) = body {
+                aabb = aabb.rotated(body.orientation);
+            }
+            world.insert(e, aabb, col.body, layer.copied().unwrap_or_default());
+        }
+    }
+}
+
+pub struct CollisionSystem;
+impl<'a> System<'a> for CollisionSystem {
+    type SystemData = Write<'a, CollisionWorld>;
+
+    fn run(&mut self, mut world: Self::SystemData) {
+        world.step();
+    }
+}
+
+pub struct ResolveCollisionSystem;
+impl<'a> System<'a> for ResolveCollisionSystem {
+    type SystemData = (
+        Entities<'a>,
+        Read<'a, CollisionWorld>,
+        ReadStorage<'a, Collider>,
+        WriteStorage<'a, Transform>,
+        WriteStorage<'a, Velocity>,
+    );
+
+    fn run(
+        &mut self,
+        (entities, world, colliders, mut transforms, mut velocities): Self::SystemData,
+    ) {
+        for (e, col, transform) in (&entities, &colliders, &mut transforms).join() {
+            if col.body != BodyType::Dynamic {
+                continue;
+            }
+            if let Some(push) = world.correction(e) {
+                transform.cur_pos = (Vec2::from(transform.cur_pos) + push).into();
+            }
+
+            // Stop pushing into whatever we were pushed out of.
+            if let Some(vel) = velocities.get_mut(e) {
+                let against_static = world.contacts_of(e).filter(|c| {
+                    colliders
+                        .get(c.other)
+                        .is_some_and(|o| o.body == BodyType::Static)
+                });
+                for contact in against_static {
+                    if contact.normal.x * vel.cur.x < 0.0 {
+                        vel.cur.x = 0.0;
+                    }
+                    if contact.normal.y * vel.cur.y < 0.0 {
+                        vel.cur.y = 0.0;
+                    }
+                }
+            }
+        }
+    }
+}
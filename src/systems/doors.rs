@@ -0,0 +1,252 @@
+use super::room_bounds;
+use crate::{
+    components::*,
+    game::DeltaTime,
+    physics::{CollisionWorld, TriggerEventKind, Vec2},
+    rooms::{arrival_point, ActiveRoom, RoomTransition, Transition, TransitionKind},
+    Camera,
+};
+use specs::{Entities, Entity, Join, Read, ReadStorage, System, Write, WriteStorage};
+
+// Starts a room transition when the player walks into a door trigger. Bottom doors are
+// trapdoors in the floor, so they are only taken while the player is trying to drop.
+pub struct DoorSystem {
+    kind: TransitionKind,
+    duration: f32,
+}
+impl DoorSystem {
+    pub fn new(kind: TransitionKind, duration: f32) -> Self {
+        Self { kind, duration }
+    }
+}
+impl<'a> System<'a> for DoorSystem {
+    type SystemData = (
+        Read<'a, CollisionWorld>,
+        Write<'a, RoomTransition>,
+        ReadStorage<'a, DoorTrigger>,
+        ReadStorage<'a, Doors>,
+        ReadStorage<'a, Player>,
+        ReadStorage<'a, IntentToDrop>,
+        ReadStorage<'a, Size>,
+    );
+
+    fn run(
+        &mut self,
+        (world, mut transition, triggers, doors, players, drops, sizes): Self::SystemData,
+    ) {
+        let RoomTransition(transition) = &mut *transition;
+        if transition.is_some() {
+            return;
+        }
+
+        for event in world.trigger_events() {
+            if !players.contains(event.other) {
+                continue;
+            }
+            let trigger = match triggers.get(event.trigger) {
+                Some(t) => t,
+                None => continue,
+            };
+            let entering = if trigger.door == DoorType::Bottom {
+                event.kind != TriggerEventKind::Exit && drops.contains(event.other)
+            } else {
+                event.kind == TriggerEventKind::Enter
+            };
+            if !entering {
+                continue;
+            }
+            let door = match doors.get(trigger.room).and_then(|d| d.0.get(&trigger.door)) {
+                Some(d) => d,
+                None => continue,
+            };
+            let exit = match matching_door(&doors, door.to_room, trigger.room, trigger.door) {
+                Some(exit) => exit,
+                None => continue,
+            };
+            let traveller_size = match sizes.get(event.other) {
+                Some(s) => s,
+                None => continue,
+            };
+
+            let arrival = arrival_point(exit.0, exit.1, traveller_size);
+            *transition = Some(Transition::new(
+                self.kind,
+                event.other,
+                door.to_room,
+                arrival,
+                self.duration,
+            ));
+            return;
+        }
+    }
+}
+
+// The door in `to_room` we come out of: the one on the opposite side if it leads back,
+// otherwise any door that leads back.
+fn matching_door(
+    doors: &ReadStorage<Doors>,
+    to_room: Entity,
+    from_room: Entity,
+    entered: DoorType,
+) -> Option<(DoorType, Position)> {
+    let Doors(to_doors) = doors.get(to_room)?;
+    if let Some(d) = to_doors.get(&entered.opposite()) {
+        if d.to_room == from_room {
+            return Some((entered.opposite(), d.pos));
+        }
+    }
+    to_doors
+        .iter()
+        .find(|(_, d)| d.to_room == from_room)
+        .map(|(t, d)| (*t, d.pos))
+}
+
+pub struct RoomTransitionSystem {
+    cam: Entity,
+}
+impl RoomTransitionSystem {
+    pub fn new(cam: Entity) -> Self {
+        Self { cam }
+    }
+}
+impl<'a> System<'a> for RoomTransitionSystem {
+    type SystemData = (
+        Read<'a, DeltaTime>,
+        Write<'a, RoomTransition>,
+        Write<'a, ActiveRoom>,
+        WriteStorage<'a, Teleport>,
+        WriteStorage<'a, Camera>,
+        ReadStorage<'a, ConfineToRoom>,
+        ReadStorage<'a, Transform>,
+        ReadStorage<'a, Size>,
+    );
+
+    fn run(
+        &mut self,
+        (
+            dt,
+            mut transition,
+            mut active_room,
+            mut teleports,
+            mut cams,
+            confined,
+            transforms,
+            sizes,
+        ): Self::SystemData,
+    ) {
+        let RoomTransition(slot) = &mut *transition;
+        let tr = match slot {
+            Some(tr) => tr,
+            None => return,
+        };
+        let DeltaTime(dt) = *dt;
+        tr.elapsed += dt;
+
+        let cam = cams.get_mut(self.cam);
+        if let (None, Some(cam)) = (tr.cam_from, &cam) {
+            // The camera's own bounds still belong to the room being left.
+            let to_room = match (transforms.get(tr.to_room), sizes.get(tr.to_room)) {
+                (Some(t), Some(s)) if confined.contains(self.cam) => Some(room_bounds(t, s)),
+                _ => None,
+            };
+            // Camera space has Y pointing down.
+            let to = Position::new(tr.arrival.x, -tr.arrival.y);
+            tr.cam_from = Some(cam.cur_pos);
+            tr.cam_to = Some(match to_room {
+                Some(bounds) => cam.clamp_within(to, bounds),
+                None => cam.clamp(to),
+            });
+        }
+
+        if !tr.arrived && tr.progress() >= tr.switch_point() {
+            teleports
+                .insert(tr.traveller, Teleport { to: tr.arrival })
+                .expect("Teleporting through a door");
+            active_room.0 = Some(tr.to_room);
+            tr.arrived = true;
+        }
+
+        if let (Some(cam), Some(cam_from), Some(cam_to)) = (cam, tr.cam_from, tr.cam_to) {
+            match tr.kind {
+                TransitionKind::Slide => {
+                    let t = tr.progress();
+                    let eased = t * t * (3.0 - 2.0 * t);
+                    cam.prev_pos = Some(cam.cur_pos);
+                    cam.cur_pos = Vec2::from(cam_from).lerp(Vec2::from(cam_to), eased).into();
+                }
+                TransitionKind::Fade => {
+                    if tr.arrived {
+                        cam.prev_pos = None;
+                        cam.cur_pos = cam_to;
+                    }
+                }
+            }
+        }
+
+        if tr.is_finished() {
+            *slot = None;
+        }
+    }
+}
+
+pub struct TeleportSystem;
+impl<'a> System<'a> for TeleportSystem {
+    type SystemData = (
+        Entities<'a>,
+        WriteStorage<'a, Teleport>,
+        WriteStorage<'a, Transform>,
+        WriteStorage<'a, Velocity>,
+    );
+
+    fn run(&mut self, (entities, mut teleports, mut transforms, mut vels): Self::SystemData) {
+        let mut done = Vec::new();
+        for (e, tp, transform) in (&entities, &teleports, &mut transforms).join() {
+            transform.cur_pos = tp.to;
+            transform.prev_pos = None;
+            if let Some(vel) = vels.get_mut(e) {
+                vel.cur = Vec2::zero();
+            }
+            done.push(e);
+        }
+        for e in done {
+            teleports.remove(e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::new_world;
+    use ggez::graphics::Rect;
+    use specs::{Builder, RunNow, WorldExt};
+
+    #[test]
+    fn slides_end_inside_the_room_being_entered() {
+        let mut world = new_world();
+        let room = world
+            .create_entity()
+            .with(Transform::new(Position::new(2000.0, 0.0)))
+            .with(Size::new(1000.0, 800.0))
+            .build();
+        let traveller = world.create_entity().build();
+        // Still bounded by the room being left.
+        let mut cam = Camera::default();
+        cam.bounds = Some(Rect::new(-500.0, -400.0, 1000.0, 800.0));
+        let cam = world.create_entity().with(cam).with(ConfineToRoom).build();
+        world.insert(DeltaTime(1.0));
+        world.insert(RoomTransition(Some(Transition::new(
+            TransitionKind::Slide,
+            traveller,
+            room,
+            Position::new(2400.0, -300.0),
+            1.0,
+        ))));
+
+        RoomTransitionSystem::new(cam).run_now(&world);
+        assert_eq!(
+            world.read_storage::<Camera>().get(cam).unwrap().cur_pos,
+            Position::new(2100.0, 100.0)
+        );
+    }
+}
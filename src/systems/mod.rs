@@ -0,0 +1,604 @@
+use super::{
+    components::*,
+    game::DeltaTime,
+    physics::{Aabb, CollisionWorld, Mat22, Vec2},
+    Camera,
+};
+use ggez::graphics::{self, spritebatch::SpriteBatch, Drawable, Rect};
+use ggez::mint::Vector2;
+use ggez::Context;
+use specs::{Entities, Join, Read, ReadStorage, System, Write, WriteStorage};
+use std::{cmp::Ordering, collections::HashSet, ops::Range};
+
+mod animation;
+pub use self::animation::*;
+mod camera;
+pub use self::camera::*;
+mod collision;
+pub use self::collision::*;
+mod doors;
+pub use self::doors::*;
+mod platformer;
+pub use self::platformer::*;
+mod rigid_body;
+pub use self::rigid_body::*;
+
+fn calc_alpha(pos_x: f32, pos_y: f32, prev_x: f32, prev_y: f32, alpha: f64) -> (f32, f32) {
+    let cur_ax = (pos_x as f64) * alpha;
+    let cur_ay = (pos_y as f64) * alpha;
+    let prev_ax = (prev_x as f64) * (1.0 - alpha);
+    let prev_ay = (prev_y as f64) * (1.0 - alpha);
+    let ax_intpol = (cur_ax + prev_ax) as f32;
+    let ay_intpol = (cur_ay + prev_ay) as f32;
+
+    (ax_intpol, ay_intpol)
+}
+
+// Where a transform is drawn, `alpha` of the way from last tick to this one.
+fn interpolate(transform: &Transform, alpha: f64) -> Position {
+    let (x, y) = (transform.cur_pos.x, transform.cur_pos.y);
+    match transform.prev_pos {
+        Some(prev) => {
+            let (x, y) = calc_alpha(x, y, prev.x, prev.y, alpha);
+            Position::new(x, y)
+        }
+        None => Position::new(x, y),
+    }
+}
+
+// Screen position of the top-left corner of an entity's `Size` box.
+// The area a room covers, in camera space. Camera space has Y pointing down, so the top
+// edge is the smallest Y.
+fn room_bounds(transform: &Transform, size: &Size) -> Rect {
+    let pos = transform.cur_pos;
+    Rect::new(
+        pos.x - size.width / 2.0,
+        -(pos.y + size.height / 2.0),
+        size.width,
+        size.height,
+    )
+}
+
+fn calc_coords(transform: &Transform, size: &Size, cam: &Camera, alpha: f64) -> (f32, f32) {
+    let pos = interpolate(transform, alpha);
+    let top_left = Position::new(pos.x - size.width / 2.0, pos.y + size.height / 2.0);
+    let view = cam.world_to_view(top_left, alpha);
+    (view.x, view.y)
+}
+
+// ggez rotates around the top-left corner, so shift the corner to keep the centre put.
+// Screen Y points down, which turns a counter-clockwise world angle into a negative one.
+fn rotate_about_center(
+    x: f32,
+    y: f32,
+    size: &Size,
+    scale: Vector2<f32>,
+    orientation: f32,
+) -> (f32, Position) {
+    let half = Vec2::new(size.width / 2.0 * scale.x, size.height / 2.0 * scale.y);
+    let rotation = -orientation;
+    let corner = Vec2::new(x, y) + half - Mat22::new_by_angle(rotation) * half;
+    (rotation, corner.into())
+}
+
+// A negative scale mirrors around the drawable's origin, so the origin moves to the
+// other side of the `Size` box to mirror about the box's centre line instead.
+fn mirror_x(
+    dest: Position,
+    size: &Size,
+    scale: Vector2<f32>,
+    rotation: f32,
+) -> (Position, Vector2<f32>) {
+    let width = size.width * scale.x;
+    let dest = Position::new(
+        dest.x + width * rotation.cos(),
+        dest.y + width * rotation.sin(),
+    );
+    (
+        dest,
+        Vector2 {
+            x: -scale.x,
+            y: scale.y,
+        },
+    )
+}
+
+// How many renderables the cameras drew and skipped this frame, and how many draw calls
+// that took.
+#[derive(Default)]
+pub struct RenderStats {
+    pub drawn: usize,
+    pub culled: usize,
+    pub draw_calls: usize,
+}
+
+pub struct RenderSystem<'a> {
+    ctx: &'a mut Context,
+    alpha: f64,
+    cam: specs::Entity,
+}
+impl<'a> RenderSystem<'a> {
+    pub fn new(ctx: &'a mut Context, alpha: f64, cam: specs::Entity) -> Self {
+        Self { ctx, alpha, cam }
+    }
+}
+impl<'a, 'b> System<'a> for RenderSystem<'b> {
+    type SystemData = (
+        Entities<'a>,
+        Option<Read<'a, CollisionWorld>>,
+        Write<'a, RenderStats>,
+        ReadStorage<'a, Camera>,
+        ReadStorage<'a, Transform>,
+        ReadStorage<'a, Sprite>,
+        ReadStorage<'a, Size>,
+        ReadStorage<'a, RigidBody>,
+        ReadStorage<'a, ViewLayer>,
+        ReadStorage<'a, Facing>,
+        ReadStorage<'a, NoFlip>,
+        ReadStorage<'a, RenderLayer>,
+    );
+
+    // Draws in the camera's view coordinates, so the caller points the screen at the
+    // camera's viewport first.
+    fn run(
+        &mut self,
+        (
+            entities,
+            world,
+            mut stats,
+            cams,
+            transforms,
+            sprites,
+            sizes,
+            bodies,
+            layers,
+            facings,
+            no_flips,
+            render_layers,
+        ): Self::SystemData,
+    ) {
+        // Collider boxes are a tick behind what is drawn, so leave some room for that.
+        const CULL_MARGIN: f32 = 50.0;
+
+        let cam = cams.get(self.cam).expect("Could not retrieve camera!");
+        let scale = cam.scale_at(self.alpha);
+        let view = cam.visible_bounds(self.alpha);
+        let view = Aabb::new(view.center, view.half + Vec2::new(CULL_MARGIN, CULL_MARGIN));
+        // Entities in the collision world are looked up through its broadphase, the rest
+        // are checked one by one.
+        let indexed: Option<HashSet<specs::Entity>> = world
+            .as_ref()
+            .map(|world| world.query_rect(&view).into_iter().collect());
+        let mut visible = Vec::new();
+
+        for (e, transform, sprite, size, body, layer, facing, no_flip, render_layer) in (
+            &entities,
+            &transforms,
+            &sprites,
+            &sizes,
+            (&bodies).maybe(),
+            (&layers).maybe(),
+            (&facings).maybe(),
+            (&no_flips).maybe(),
+            (&render_layers).maybe(),
+        )
+            .join()
+        {
+            if !ViewLayer::visible_to(layer, cam.layer_mask) {
+                continue;
+            }
+            let pos = interpolate(transform, self.alpha);
+            let on_screen = match (&world, &indexed) {
+                (Some(world), Some(indexed)) if world.aabb(e).is_some() => indexed.contains(&e),
+                _ => {
+                    let pos = Vec2::from(pos);
+                    let bounds = Aabb::from_size(pos, size.width, size.height);
+                    let bounds = body.map_or(bounds, |b| bounds.rotated(b.orientation));
+                    bounds.overlaps(&view)
+                }
+            };
+            if !on_screen {
+                stats.culled += 1;
+                continue;
+            }
+            stats.drawn += 1;
+
+            let (x, y) = calc_coords(transform, size, cam, self.alpha);
+            let (rotation, dest) = match body {
+                Some(body) => rotate_about_center(x, y, size, scale, body.orientation),
+                None => (0.0, Position::new(x, y)),
+            };
+            // Sprites are drawn facing right.
+            let flip = no_flip.is_none() && facing.is_some_and(|f| f.direction == Direction::Left);
+            let (dest, scale) = if flip {
+                mirror_x(dest, size, scale, rotation)
+            } else {
+                (dest, scale)
+            };
+            let mut draw_param = graphics::DrawParam::default()
+                .dest(dest)
+                .rotation(rotation)
+                .scale(scale);
+            if let Some(dp) = sprite.draw_param {
+                draw_param = dp.dest(dest).rotation(rotation).scale(scale);
+            }
+            let order = RenderLayer::sort_key(render_layer, pos.y - size.height / 2.0);
+            visible.push((order, (draw_param, &sprite.visual)));
+        }
+
+        sort_for_drawing(&mut visible);
+
+        let images: Vec<Option<&graphics::Image>> = visible
+            .iter()
+            .map(|(_, (_, visual))| match visual {
+                Visual::Image(image) => Some(image),
+                _ => None,
+            })
+            .collect();
+        // Image equality covers the texture, sampler and blend mode, which is everything
+        // the sprites of a batch have to share. One batch serves every batched call of
+        // this view; it is dropped with the system at the end of the pass.
+        let mut batch: Option<SpriteBatch> = None;
+        for call in draw_calls(&images, |a, b| a == b) {
+            stats.draw_calls += 1;
+            let (_, (draw_param, visual)) = visible[call.start];
+            if call.len() > 1 {
+                let image = images[call.start].expect("Batching an image");
+                let params = visible[call].iter().map(|(_, (param, _))| *param);
+                draw_batch(self.ctx, &mut batch, image, params);
+                continue;
+            }
+            match visual {
+                Visual::Image(image) => {
+                    graphics::draw(self.ctx, image, draw_param).expect("Drawing an image")
+                }
+                Visual::Mesh(mesh) => {
+                    graphics::draw(self.ctx, mesh, draw_param).expect("Drawing a mesh")
+                }
+                Visual::Text { content, font_size } => {
+                    let fragment = graphics::TextFragment::new(content.as_str())
+                        .scale(graphics::Scale::uniform(*font_size));
+                    graphics::draw(self.ctx, &graphics::Text::new(fragment), draw_param)
+                        .expect("Drawing text");
+                }
+            }
+        }
+    }
+}
+
+// Puts items keyed by `RenderLayer::sort_key` in the order they are drawn. The sort is
+// stable, so items that tie keep the order they were joined in.
+fn sort_for_drawing<T>(items: &mut [((i32, f32), T)]) {
+    items.sort_by(|(a, _), (b, _)| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+}
+
+// Splits the draw order into draw calls, each a range of `items`. Images that come one
+// after another and can share a batch go in one call, everything else (`None`) is drawn
+// on its own.
+fn draw_calls<T>(items: &[Option<T>], same_batch: impl Fn(&T, &T) -> bool) -> Vec<Range<usize>> {
+    let mut calls: Vec<Range<usize>> = Vec::new();
+    for (i, item) in items.iter().enumerate() {
+        if let (Some(item), Some(last)) = (item, calls.last_mut()) {
+            if let Some(prev) = &items[last.start] {
+                if same_batch(prev, item) {
+                    last.end = i + 1;
+                    continue;
+                }
+            }
+        }
+        calls.push(i..i + 1);
+    }
+    calls
+}
+
+// Draws `image` once for every param through `batch`. The caller keeps the batch for the
+// rest of the pass, so later batched calls in the same view reuse its buffer.
+fn draw_batch(
+    ctx: &mut Context,
+    batch: &mut Option<SpriteBatch>,
+    image: &graphics::Image,
+    params: impl Iterator<Item = graphics::DrawParam>,
+) {
+    let batch = match batch {
+        Some(batch) => {
+            batch.set_image(image.clone());
+            batch
+        }
+        None => batch.get_or_insert_with(|| SpriteBatch::new(image.clone())),
+    };
+    // The batch draws with its own blend mode rather than its image's.
+    batch.set_blend_mode(image.blend_mode());
+    for param in params {
+        batch.add(param);
+    }
+    graphics::draw(ctx, &*batch, graphics::DrawParam::default()).expect("Drawing a sprite batch");
+    batch.clear();
+}
+
+pub struct MoveSystem;
+impl<'a> System<'a> for MoveSystem {
+    type SystemData = (
+        ReadStorage<'a, IntentToMove>,
+        WriteStorage<'a, Acceleration>,
+    );
+
+    fn run(&mut self, (intentions, mut accelerations): Self::SystemData) {
+        for (acc, move_int) in (&mut accelerations, (&intentions).maybe()).join() {
+            acc.cur.set(0.0, 0.0);
+            if let Some(IntentToMove(moves)) = move_int {
+                for m in moves.iter() {
+                    use Direction::*;
+                    match m {
+                        Right => acc.cur.x += acc.rate,
+                        Left => acc.cur.x -= acc.rate,
+                        Up => acc.cur.y += acc.rate,
+                        Down => acc.cur.y -= acc.rate,
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn integrate_axis(vel: f32, acc: f32, max_speed: f32, friction: f32, dt: f32) -> f32 {
+    // Below this speed a decelerating axis is considered to be at rest.
+    const REST_SPEED: f32 = 1.0;
+
+    let vel = if acc == 0.0 {
+        let damped = vel - vel * (friction * dt).min(1.0);
+        if damped.abs() < REST_SPEED {
+            0.0
+        } else {
+            damped
+        }
+    } else {
+        vel + acc * dt
+    };
+
+    vel.max(-max_speed).min(max_speed)
+}
+
+pub struct IntegrateSystem;
+impl<'a> System<'a> for IntegrateSystem {
+    type SystemData = (
+        Read<'a, DeltaTime>,
+        ReadStorage<'a, Acceleration>,
+        WriteStorage<'a, Velocity>,
+        WriteStorage<'a, Transform>,
+    );
+
+    fn run(&mut self, (dt, accelerations, mut velocities, mut transforms): Self::SystemData) {
+        let DeltaTime(dt) = *dt;
+        for (acc, vel, transform) in
+            ((&accelerations).maybe(), &mut velocities, &mut transforms).join()
+        {
+            let (acc_x, acc_y) = acc.map_or((0.0, 0.0), |a| (a.cur.x, a.cur.y));
+            vel.cur.x = integrate_axis(vel.cur.x, acc_x, vel.max_speed.x, vel.friction, dt);
+            vel.cur.y = integrate_axis(vel.cur.y, acc_y, vel.max_speed.y, vel.friction, dt);
+
+            transform.prev_pos = Some(transform.cur_pos);
+            transform.cur_pos.x += vel.cur.x * dt;
+            transform.cur_pos.y += vel.cur.y * dt;
+        }
+    }
+}
+
+pub struct StopMovingSystem;
+impl<'a> System<'a> for StopMovingSystem {
+    type SystemData = (
+        WriteStorage<'a, Transform>,
+        ReadStorage<'a, IntentToMove>,
+        ReadStorage<'a, Velocity>,
+    );
+
+    // Entities with a `Velocity` keep their interpolation state while they slow down,
+    // so only the ones moved by hand are reset here.
+    fn run(&mut self, (mut transforms, int_moves, velocities): Self::SystemData) {
+        for (transform, im, _) in (&mut transforms, (&int_moves).maybe(), !&velocities).join() {
+            if im.is_none() && transform.prev_pos.is_some() {
+                transform.prev_pos = None;
+            }
+        }
+    }
+}
+
+pub struct MoveCamSystem;
+impl<'a> System<'a> for MoveCamSystem {
+    type SystemData = (WriteStorage<'a, Camera>, ReadStorage<'a, IntentToMove>);
+
+    fn run(&mut self, (mut cams, int_moves): Self::SystemData) {
+        const SPEED: f32 = 5.0;
+        for (cam, moves) in (&mut cams, &int_moves).join() {
+            let IntentToMove(moves) = moves;
+            cam.prev_pos = Some(cam.cur_pos);
+            for m in moves.iter() {
+                use Direction::*;
+                match m {
+                    Up => {
+                        cam.cur_pos.y -= SPEED;
+                    }
+                    Down => {
+                        cam.cur_pos.y += SPEED;
+                    }
+                    Left => {
+                        cam.cur_pos.x -= SPEED;
+                    }
+                    Right => {
+                        cam.cur_pos.x += SPEED;
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::FRAC_PI_2;
+
+    fn close(a: Position, b: Position) -> bool {
+        (a.x - b.x).abs() < 1e-4 && (a.y - b.y).abs() < 1e-4
+    }
+
+    // Where a point of the drawable, in its own unscaled coordinates, ends up on screen.
+    fn drawn(dest: Position, scale: Vector2<f32>, rotation: f32, local: Position) -> Position {
+        let (x, y) = (local.x * scale.x, local.y * scale.y);
+        let (sin, cos) = rotation.sin_cos();
+        Position::new(dest.x + x * cos - y * sin, dest.y + x * sin + y * cos)
+    }
+
+    #[test]
+    fn mirroring_flips_the_scale_and_moves_the_origin_across() {
+        let size = Size::new(20.0, 10.0);
+        let scale = Vector2 { x: 2.0, y: 3.0 };
+        let (dest, mirrored) = mirror_x(Position::new(100.0, 50.0), &size, scale, 0.0);
+        assert_eq!(dest, Position::new(140.0, 50.0));
+        assert_eq!((mirrored.x, mirrored.y), (-2.0, 3.0));
+    }
+
+    #[test]
+    fn mirrored_sprites_cover_the_same_box_with_sides_swapped() {
+        let size = Size::new(20.0, 10.0);
+        let scale = Vector2 { x: 2.0, y: 1.0 };
+        for &rotation in [0.0, 0.3, FRAC_PI_2, 2.0].iter() {
+            let at = Position::new(100.0, 50.0);
+            let (dest, mirrored) = mirror_x(at, &size, scale, rotation);
+            let (left, right) = (Position::new(0.0, 5.0), Position::new(20.0, 5.0));
+            assert!(close(
+                drawn(dest, mirrored, rotation, left),
+                drawn(at, scale, rotation, right)
+            ));
+            assert!(close(
+                drawn(dest, mirrored, rotation, right),
+                drawn(at, scale, rotation, left)
+            ));
+        }
+    }
+
+    #[test]
+    fn mirroring_twice_is_a_no_op() {
+        let size = Size::new(16.0, 16.0);
+        let scale = Vector2 { x: 1.5, y: 1.5 };
+        let at = Position::new(-30.0, 12.0);
+        let (dest, mirrored) = mirror_x(at, &size, scale, 0.7);
+        let (dest, back) = mirror_x(dest, &size, mirrored, 0.7);
+        assert!(close(dest, at));
+        assert_eq!((back.x, back.y), (scale.x, scale.y));
+    }
+
+    // Stand-ins for images: a texture name and a blend mode.
+    const HERO: Option<(&str, &str)> = Some(("hero", "alpha"));
+    const HERO_ADD: Option<(&str, &str)> = Some(("hero", "add"));
+    const TILE: Option<(&str, &str)> = Some(("tile", "alpha"));
+
+    fn calls(items: &[Option<(&str, &str)>]) -> Vec<Range<usize>> {
+        draw_calls(items, |a, b| a == b)
+    }
+
+    #[test]
+    fn consecutive_matching_images_share_a_call() {
+        assert_eq!(calls(&[HERO, HERO, HERO, TILE, TILE]), vec![0..3, 3..5]);
+    }
+
+    #[test]
+    fn anything_else_in_between_splits_a_run() {
+        assert_eq!(
+            calls(&[HERO, None, HERO, HERO, None, None]),
+            vec![0..1, 1..2, 2..4, 4..5, 5..6]
+        );
+    }
+
+    #[test]
+    fn runs_only_group_neighbours() {
+        // Batching the two heroes together would draw the tile over both.
+        assert_eq!(calls(&[HERO, TILE, HERO]).len(), 3);
+    }
+
+    #[test]
+    fn blend_modes_have_to_match() {
+        assert_eq!(
+            calls(&[HERO, HERO_ADD, HERO_ADD, HERO]),
+            vec![0..1, 1..3, 3..4]
+        );
+    }
+
+    #[test]
+    fn nothing_to_draw_takes_no_calls() {
+        assert!(calls(&[]).is_empty());
+    }
+
+    // Names in the order the renderer would draw them.
+    fn draw_order(entities: &[(&'static str, Option<RenderLayer>, f32)]) -> Vec<&'static str> {
+        let mut keyed: Vec<_> = entities
+            .iter()
+            .map(|(name, layer, bottom)| (RenderLayer::sort_key(layer.as_ref(), *bottom), *name))
+            .collect();
+        sort_for_drawing(&mut keyed);
+        keyed.into_iter().map(|(_, name)| name).collect()
+    }
+
+    #[test]
+    fn layers_draw_from_lowest_to_highest() {
+        let order = draw_order(&[
+            ("ui", Some(RenderLayer::new(RenderLayer::UI)), 0.0),
+            ("prop", None, 0.0),
+            (
+                "background",
+                Some(RenderLayer::new(RenderLayer::BACKGROUND)),
+                0.0,
+            ),
+            (
+                "player",
+                Some(RenderLayer::y_sorted(RenderLayer::CHARACTERS)),
+                0.0,
+            ),
+        ]);
+        assert_eq!(order, vec!["background", "prop", "player", "ui"]);
+    }
+
+    #[test]
+    fn y_sorted_entities_lower_down_go_in_front() {
+        let chars = Some(RenderLayer::y_sorted(RenderLayer::CHARACTERS));
+        let order = draw_order(&[
+            ("low", chars, -50.0),
+            ("high", chars, 80.0),
+            ("mid", chars, 0.0),
+        ]);
+        assert_eq!(order, vec!["high", "mid", "low"]);
+    }
+
+    #[test]
+    fn y_sorting_stays_within_its_layer() {
+        let order = draw_order(&[
+            (
+                "enemy",
+                Some(RenderLayer::y_sorted(RenderLayer::CHARACTERS)),
+                -1000.0,
+            ),
+            (
+                "tree",
+                Some(RenderLayer::y_sorted(RenderLayer::FOREGROUND)),
+                1000.0,
+            ),
+            (
+                "sign",
+                Some(RenderLayer::new(RenderLayer::CHARACTERS)),
+                -2000.0,
+            ),
+        ]);
+        // Unsorted entities of a layer go under its y-sorted ones.
+        assert_eq!(order, vec!["sign", "enemy", "tree"]);
+    }
+
+    #[test]
+    fn ties_keep_their_join_order() {
+        let props = Some(RenderLayer::new(RenderLayer::PROPS));
+        let order = draw_order(&[
+            ("first", props, 10.0),
+            ("second", None, -5.0),
+            ("third", props, 0.0),
+        ]);
+        assert_eq!(order, vec!["first", "second", "third"]);
+    }
+}
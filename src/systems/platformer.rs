@@ -0,0 +1,44 @@
+use crate::{
+    components::*,
+    game::DeltaTime,
+    physics::{BodyType, CollisionWorld},
+};
+use specs::{Entities, Join, Read, ReadStorage, System, WriteStorage};
+
+// Anything pushed out of the ground at a steeper angle than this is standing on it.
+const GROUND_NORMAL_Y: f32 = 0.7;
+
+pub struct PlatformerSystem;
+impl<'a> System<'a> for PlatformerSystem {
+    type SystemData = (
+        Entities<'a>,
+        Read<'a, DeltaTime>,
+        Read<'a, CollisionWorld>,
+        ReadStorage<'a, Collider>,
+        ReadStorage<'a, IntentToJump>,
+        WriteStorage<'a, PlatformerController>,
+        WriteStorage<'a, Velocity>,
+        WriteStorage<'a, Acceleration>,
+    );
+
+    fn run(
+        &mut self,
+        (entities, dt, world, colliders, jumps, mut controllers, mut vels, mut accs): Self::SystemData,
+    ) {
+        let DeltaTime(dt) = *dt;
+        for (e, ctrl, vel, acc) in (&entities, &mut controllers, &mut vels, &mut accs).join() {
+            // Contacts are from the previous step, which is what we are standing on now.
+            let grounded = world.contacts_of(e).any(|c| {
+                c.normal.y > GROUND_NORMAL_Y
+                    && colliders
+                        .get(c.other)
+                        .is_some_and(|o| o.body == BodyType::Static)
+            });
+
+            if ctrl.update_jump(grounded, jumps.contains(e), dt) {
+                vel.cur.y = ctrl.jump_speed;
+            }
+            acc.cur.y = -ctrl.current_gravity(vel.cur.y);
+        }
+    }
+}
@@ -0,0 +1,107 @@
+use crate::{
+    components::*,
+    game::DeltaTime,
+    physics::{
+        apply_contact_impulse, positional_correction, BodyState, BodyType, CollisionWorld, Vec2,
+    },
+};
+use specs::{Entity, Join, Read, ReadStorage, System, Write, WriteStorage};
+
+pub struct RigidBodySystem;
+impl<'a> System<'a> for RigidBodySystem {
+    type SystemData = (
+        Read<'a, DeltaTime>,
+        WriteStorage<'a, RigidBody>,
+        WriteStorage<'a, Acceleration>,
+    );
+
+    fn run(&mut self, (dt, mut bodies, mut accs): Self::SystemData) {
+        let DeltaTime(dt) = *dt;
+        for (body, acc) in (&mut bodies, (&mut accs).maybe()).join() {
+            if let Some(acc) = acc {
+                acc.cur.y -= body.gravity;
+            }
+            body.angular_velocity *= (1.0 - body.angular_damping * dt).max(0.0);
+            body.orientation += body.angular_velocity * dt;
+        }
+    }
+}
+
+fn body_state(
+    e: Entity,
+    world: &CollisionWorld,
+    colliders: &ReadStorage<Collider>,
+    bodies: &WriteStorage<RigidBody>,
+    vels: &WriteStorage<Velocity>,
+) -> Option<BodyState> {
+    let center = world.aabb(e)?.center;
+    let velocity = vels.get(e).map_or(Vec2::zero(), |v| v.cur);
+    match (colliders.get(e), bodies.get(e)) {
+        (Some(col), Some(body)) if col.body == BodyType::Dynamic => Some(BodyState {
+            center,
+            velocity,
+            angular_velocity: body.angular_velocity,
+            inv_mass: body.inv_mass,
+            inv_inertia: body.inv_inertia,
+            restitution: body.restitution,
+        }),
+        // Static and plain dynamic bodies push rigid bodies around but never give way.
+        (Some(_), _) => Some(BodyState::immovable(center, velocity)),
+        _ => None,
+    }
+}
+
+fn store_state(
+    e: Entity,
+    state: &BodyState,
+    bodies: &mut WriteStorage<RigidBody>,
+    vels: &mut WriteStorage<Velocity>,
+) {
+    if let Some(body) = bodies.get_mut(e) {
+        body.angular_velocity = state.angular_velocity;
+        if let Some(vel) = vels.get_mut(e) {
+            vel.cur = state.velocity;
+        }
+    }
+}
+
+// Resolves every contact that involves at least one rigid body with impulses, and
+// separates overlapping dynamic pairs in proportion to their mass.
+pub struct ImpulseSystem;
+impl<'a> System<'a> for ImpulseSystem {
+    type SystemData = (
+        Write<'a, CollisionWorld>,
+        ReadStorage<'a, Collider>,
+        WriteStorage<'a, RigidBody>,
+        WriteStorage<'a, Velocity>,
+    );
+
+    fn run(&mut self, (mut world, colliders, mut bodies, mut vels): Self::SystemData) {
+        let contacts = world.contacts().to_vec();
+        for c in contacts {
+            if !bodies.contains(c.entity) && !bodies.contains(c.other) {
+                continue;
+            }
+            let a = body_state(c.entity, &world, &colliders, &bodies, &vels);
+            let b = body_state(c.other, &world, &colliders, &bodies, &vels);
+            let (mut a, mut b) = match (a, b) {
+                (Some(a), Some(b)) => (a, b),
+                _ => continue,
+            };
+
+            apply_contact_impulse(&mut a, &mut b, c.normal, c.point);
+            store_state(c.entity, &a, &mut bodies, &mut vels);
+            store_state(c.other, &b, &mut bodies, &mut vels);
+
+            // Static contacts were already separated by the collision step.
+            let other_static = colliders
+                .get(c.other)
+                .is_some_and(|o| o.body == BodyType::Static);
+            if !other_static {
+                let (push_a, push_b) = positional_correction(&a, &b, c.normal, c.depth);
+                world.add_correction(c.entity, push_a);
+                world.add_correction(c.other, push_b);
+            }
+        }
+    }
+}